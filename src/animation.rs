@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    //stops on the last frame and reports itself finished
+    Once,
+}
+
+//sprite is the name of a region in the texture atlas, duration is in seconds
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub sprite: &'static str,
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    pub frames: Vec<Frame>,
+    pub mode: PlayMode,
+}
+
+impl AnimationClip {
+    pub fn new(mode: PlayMode, frames: &[(&'static str, f32)]) -> Self {
+        Self {
+            frames: frames
+                .iter()
+                .map(|&(sprite, duration)| Frame { sprite, duration })
+                .collect(),
+            mode,
+        }
+    }
+}
+
+pub type AnimationSet = HashMap<&'static str, AnimationClip>;
+
+pub fn player_clips() -> AnimationSet {
+    let mut clips = AnimationSet::new();
+    clips.insert(
+        "idle",
+        AnimationClip::new(
+            PlayMode::Loop,
+            &[("player_idle_0", 0.6), ("player_idle_1", 0.4)],
+        ),
+    );
+    clips.insert(
+        "run",
+        AnimationClip::new(
+            PlayMode::Loop,
+            &[
+                ("player_run_0", 0.1),
+                ("player_run_1", 0.1),
+                ("player_run_2", 0.1),
+                ("player_run_3", 0.1),
+            ],
+        ),
+    );
+    clips.insert(
+        "jump",
+        AnimationClip::new(PlayMode::Once, &[("player_jump_0", 0.1)]),
+    );
+    clips.insert(
+        "fall",
+        AnimationClip::new(PlayMode::Once, &[("player_fall_0", 0.1)]),
+    );
+    clips.insert(
+        "shoot",
+        AnimationClip::new(
+            PlayMode::Once,
+            &[("player_shoot_0", 0.05), ("player_shoot_1", 0.1)],
+        ),
+    );
+    clips
+}
+
+//where one entity is in its current clip
+pub struct Animator {
+    clip: &'static str,
+    frame: usize,
+    elapsed: f32,
+    finished: bool,
+}
+
+impl Animator {
+    pub fn new(clip: &'static str) -> Self {
+        Self {
+            clip,
+            frame: 0,
+            elapsed: 0.0,
+            finished: false,
+        }
+    }
+
+    pub fn clip(&self) -> &'static str {
+        self.clip
+    }
+
+    //switches clips, playing the clip that's already running keeps it going
+    pub fn play(&mut self, clip: &'static str) {
+        if self.clip != clip {
+            *self = Self::new(clip);
+        }
+    }
+
+    //starts the clip over even if it's the one already playing
+    pub fn restart(&mut self, clip: &'static str) {
+        *self = Self::new(clip);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn advance(&mut self, clips: &AnimationSet, dt: f32) {
+        let clip = match clips.get(self.clip) {
+            Some(clip) if !clip.frames.is_empty() => clip,
+            _ => return,
+        };
+
+        self.elapsed += dt;
+        //frames without a duration would spin forever, they just stay up
+        while !self.finished
+            && clip.frames[self.frame].duration > 0.0
+            && self.elapsed >= clip.frames[self.frame].duration
+        {
+            self.elapsed -= clip.frames[self.frame].duration;
+            if self.frame + 1 < clip.frames.len() {
+                self.frame += 1;
+            } else {
+                match clip.mode {
+                    PlayMode::Loop => self.frame = 0,
+                    PlayMode::Once => self.finished = true,
+                }
+            }
+        }
+    }
+
+    //sprite of the current frame, None when the clip doesn't exist
+    pub fn sprite(&self, clips: &AnimationSet) -> Option<&'static str> {
+        clips
+            .get(self.clip)
+            .and_then(|clip| clip.frames.get(self.frame))
+            .map(|frame| frame.sprite)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::data::ParseError;
+use crate::level::{BlockDef, EntityDef, EntityKind, Level};
+use crate::world::QUAD_SIZE;
+
+//plain text levels where every character is one tile, the first line is the top of the level
+//
+//  #  solid tile
+//  P  player spawn
+//  E  enemy
+//  o  pickup
+//  .  or space, empty
+//
+//....................
+//.....P..............
+//....######....E.....
+//##########o#########
+pub fn parse(source: &str) -> Result<Level, ParseError> {
+    let rows: Vec<&str> = source.lines().collect();
+    let height = rows.len();
+
+    let mut solid = Vec::with_capacity(height);
+    let mut spawn = None;
+    let mut entities = Vec::new();
+
+    for (row, line) in rows.iter().enumerate() {
+        let mut solid_row = Vec::with_capacity(line.len());
+        for (column, tile) in line.chars().enumerate() {
+            //bottom left corner of the tile in pixels, rows count down from the top
+            let pos = (
+                column as f32 * QUAD_SIZE,
+                (height - 1 - row) as f32 * QUAD_SIZE,
+            );
+
+            solid_row.push(tile == '#');
+            match tile {
+                '#' | '.' | ' ' => {}
+                'P' => {
+                    if spawn.is_some() {
+                        return Err(ParseError::new(row, column, "second spawn point"));
+                    }
+                    spawn = Some(pos);
+                }
+                'E' => entities.push(EntityDef::new(EntityKind::Enemy, pos)),
+                'o' => entities.push(EntityDef::new(EntityKind::Pickup, pos)),
+                _ => {
+                    return Err(ParseError::new(
+                        row,
+                        column,
+                        &format!("unknown tile '{}'", tile),
+                    ))
+                }
+            }
+        }
+        solid.push(solid_row);
+    }
+
+    let spawn = spawn.ok_or_else(|| ParseError::invalid("map has no spawn point 'P'"))?;
+
+    Ok(Level {
+        spawn,
+        blocks: merge_tiles(&solid),
+        entities,
+    })
+}
+
+//turns a grid of solid tiles (first row is the top) into blocks by growing each block as
+//far right as it goes and then up while the whole width stays solid. that's greedy and not
+//always the fewest blocks, but starting from the bottom keeps a floor in one piece instead
+//of cutting it up under everything standing on it
+pub(crate) fn merge_tiles(solid: &[Vec<bool>]) -> Vec<BlockDef> {
+    let height = solid.len();
+    let is_solid = |row: usize, column: usize| solid[row].get(column).copied().unwrap_or(false);
+
+    let mut taken: Vec<Vec<bool>> = solid.iter().map(|row| vec![false; row.len()]).collect();
+    let mut blocks = Vec::new();
+
+    for row in (0..height).rev() {
+        for column in 0..solid[row].len() {
+            if !is_solid(row, column) || taken[row][column] {
+                continue;
+            }
+
+            let mut width = 1;
+            while is_solid(row, column + width) && !taken[row][column + width] {
+                width += 1;
+            }
+
+            let mut rows_up = 1;
+            while rows_up <= row
+                && (column..column + width)
+                    .all(|x| is_solid(row - rows_up, x) && !taken[row - rows_up][x])
+            {
+                rows_up += 1;
+            }
+
+            let top = row + 1 - rows_up;
+            for taken_row in taken.iter_mut().skip(top).take(rows_up) {
+                for tile in taken_row.iter_mut().skip(column).take(width) {
+                    *tile = true;
+                }
+            }
+
+            //the block's position is its bottom left corner, which is the row it started on
+            blocks.push(BlockDef {
+                pos: (
+                    column as f32 * QUAD_SIZE,
+                    (height - 1 - row) as f32 * QUAD_SIZE,
+                ),
+                length: (width, rows_up),
+                color: (1.0, 1.0, 1.0),
+            });
+        }
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(level: &Level) -> Vec<((f32, f32), (usize, usize))> {
+        level.blocks.iter().map(|block| (block.pos, block.length)).collect()
+    }
+
+    #[test]
+    fn solid_tiles_merge_into_rectangles() {
+        let level = parse(
+            "\
+P.....
+.##...
+.##.##
+######",
+        )
+        .unwrap();
+
+        //the floor, the 2x2 square on it and the 2x1 step, rows above stop a block
+        //growing up unless they cover its whole width
+        assert_eq!(
+            blocks(&level),
+            vec![
+                ((0.0, 0.0), (6, 1)),
+                ((50.0, 50.0), (2, 2)),
+                ((200.0, 50.0), (2, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn floors_stay_whole_under_towers() {
+        let level = parse(include_str!("../levels/towers.txt")).unwrap();
+
+        assert_eq!(level.blocks.len(), 5);
+        assert!(blocks(&level).contains(&((0.0, 0.0), (24, 2))));
+    }
+
+    #[test]
+    fn rows_count_down_from_the_top() {
+        let level = parse(
+            "\
+..o.
+P..E
+####",
+        )
+        .unwrap();
+
+        assert_eq!(level.spawn, (0.0, 50.0));
+        let entities: Vec<(EntityKind, (f32, f32))> = level
+            .entities
+            .iter()
+            .map(|entity| (entity.kind, entity.pos))
+            .collect();
+        assert_eq!(
+            entities,
+            vec![
+                (EntityKind::Pickup, (100.0, 100.0)),
+                (EntityKind::Enemy, (150.0, 50.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn second_spawn_is_rejected() {
+        let err = parse("P..\n..P\n###").unwrap_err();
+        assert_eq!(err.position, Some((2, 3)));
+        assert_eq!(err.message, "second spawn point");
+    }
+
+    #[test]
+    fn missing_spawn_has_no_position() {
+        let err = parse("...\n###").unwrap_err();
+        assert_eq!(err.position, None);
+        assert_eq!(err.message, "map has no spawn point 'P'");
+    }
+
+    #[test]
+    fn unknown_tiles_are_rejected() {
+        let err = parse("P..\n#x#").unwrap_err();
+        assert_eq!(err.position, Some((2, 2)));
+        assert_eq!(err.message, "unknown tile 'x'");
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+
+//empty pixels left around every sprite so neighbours don't bleed into each other
+const PADDING: u32 = 1;
+const MIN_ATLAS_WIDTH: u32 = 256;
+//name of the plain white region untextured quads sample, tinting it gives a flat color
+pub const WHITE: &str = "white";
+
+//part of the atlas texture in uv coordinates, min is the top left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+impl UvRect {
+    //same region mirrored left to right
+    pub fn flipped_x(&self) -> Self {
+        Self {
+            min: [self.max[0], self.min[1]],
+            max: [self.min[0], self.max[1]],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AtlasError::Io { path, source } => {
+                write!(f, "couldn't read sprites from {}: {}", path.display(), source)
+            }
+            AtlasError::Image { path, source } => {
+                write!(f, "couldn't load sprite {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AtlasError::Io { source, .. } => Some(source),
+            AtlasError::Image { source, .. } => Some(source),
+        }
+    }
+}
+
+//every sprite the game uses packed into one texture so all quads can still be drawn at once
+pub struct TextureAtlas {
+    pub image: RgbaImage,
+    regions: HashMap<String, UvRect>,
+}
+
+impl TextureAtlas {
+    //packs every png in the directory, sprites are named after the file without the extension
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, AtlasError> {
+        let dir = dir.as_ref();
+        let io_error = |source| AtlasError::Io {
+            path: dir.to_path_buf(),
+            source,
+        };
+
+        let mut sprites = Vec::new();
+        for entry in fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("png") {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            let sprite = image::open(&path)
+                .map_err(|source| AtlasError::Image {
+                    path: path.clone(),
+                    source,
+                })?
+                .to_rgba8();
+            sprites.push((name, sprite));
+        }
+
+        Ok(Self::pack(sprites))
+    }
+
+    //shelf packing: sprites sorted by height are laid out left to right in rows
+    pub fn pack(mut sprites: Vec<(String, RgbaImage)>) -> Self {
+        sprites.push((WHITE.to_string(), RgbaImage::from_pixel(2, 2, Rgba([255; 4]))));
+        sprites.sort_by(|a, b| b.1.height().cmp(&a.1.height()).then(a.0.cmp(&b.0)));
+
+        let widest = sprites.iter().map(|(_, sprite)| sprite.width()).max().unwrap_or(0);
+        let area: u32 = sprites
+            .iter()
+            .map(|(_, sprite)| (sprite.width() + PADDING) * (sprite.height() + PADDING))
+            .sum();
+        let width = ((area as f32).sqrt() as u32)
+            .max(widest + PADDING)
+            .max(MIN_ATLAS_WIDTH)
+            .next_power_of_two();
+
+        let mut placements = Vec::with_capacity(sprites.len());
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for (_, sprite) in sprites.iter() {
+            if x + sprite.width() > width {
+                x = 0;
+                y += shelf_height + PADDING;
+                shelf_height = 0;
+            }
+            placements.push((x, y));
+            x += sprite.width() + PADDING;
+            shelf_height = shelf_height.max(sprite.height());
+        }
+        let height = (y + shelf_height).next_power_of_two();
+
+        let mut image = RgbaImage::new(width, height);
+        let mut regions = HashMap::new();
+        for ((name, sprite), (x, y)) in sprites.into_iter().zip(placements) {
+            image::imageops::replace(&mut image, &sprite, x as i64, y as i64);
+
+            let mut region = UvRect {
+                min: [x as f32 / width as f32, y as f32 / height as f32],
+                max: [
+                    (x + sprite.width()) as f32 / width as f32,
+                    (y + sprite.height()) as f32 / height as f32,
+                ],
+            };
+            //sampling the middle of the white square keeps filtering from reaching its edges
+            if name == WHITE {
+                let center = [
+                    (x as f32 + 1.0) / width as f32,
+                    (y as f32 + 1.0) / height as f32,
+                ];
+                region = UvRect {
+                    min: center,
+                    max: center,
+                };
+            }
+            regions.insert(name, region);
+        }
+
+        Self { image, regions }
+    }
+
+    pub fn region(&self, name: &str) -> Option<UvRect> {
+        self.regions.get(name).copied()
+    }
+
+    //the named sprite, or plain white if the atlas doesn't have it
+    pub fn region_or_white(&self, name: &str) -> UvRect {
+        self.region(name).unwrap_or_else(|| self.white())
+    }
+
+    pub fn white(&self) -> UvRect {
+        self.regions[WHITE]
+    }
+}
@@ -0,0 +1,501 @@
+use serde::Deserialize;
+
+//what ticking a node did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    //not done yet, it gets ticked again next time
+    Running,
+}
+
+//things an enemy can check, a condition node succeeds when it's true
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Condition {
+    SeesPlayer,
+    //saw the player recently enough to still be after them
+    RemembersPlayer,
+    //pixels between the enemy's middle and the player's
+    PlayerWithin(f32),
+    HasWeapon,
+    OnGround,
+    //health is below this fraction of the max
+    Hurt(f32),
+}
+
+//things an enemy can do
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Action {
+    //walks back and forth turning at walls and edges, never finishes
+    Patrol,
+    //goes where the player was last seen across platforms, succeeds once it's there
+    Chase,
+    //turns to the player and fires, fails while the weapon isn't ready
+    Shoot,
+}
+
+//a behavior tree as it's written in the data files, composites tick their children in
+//order every time so conditions further up are checked again each tick
+//
+//Selector([
+//    Sequence([
+//        Condition(SeesPlayer),
+//        Action(Shoot),
+//    ]),
+//    Action(Patrol),
+//])
+#[derive(Debug, Clone, Deserialize)]
+pub enum NodeDef {
+    //succeeds when every child does, stops at the first one that doesn't
+    Sequence(Vec<NodeDef>),
+    //succeeds with the first child that doesn't fail
+    Selector(Vec<NodeDef>),
+    //ticks every child, fails if one does and succeeds once all of them have
+    Parallel(Vec<NodeDef>),
+    //swaps success and failure
+    Invert(Box<NodeDef>),
+    //succeeds whatever the child does, unless it's still running
+    Succeed(Box<NodeDef>),
+    //fails for this many seconds after the child succeeded
+    Cooldown(f32, Box<NodeDef>),
+    //runs for this many seconds, the time starts over when it wasn't running the tick before
+    Wait(f32),
+    Condition(Condition),
+    Action(Action),
+}
+
+//what the tree asks about and tells to do things, an enemy for example
+pub trait Agent {
+    fn check(&mut self, condition: Condition) -> bool;
+    fn act(&mut self, action: Action) -> Status;
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Sequence,
+    Selector,
+    Parallel,
+    Invert,
+    Succeed,
+    Cooldown(f32),
+    Wait(f32),
+    Condition(Condition),
+    Action(Action),
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    kind: Kind,
+    children: Vec<usize>,
+    parent: Option<usize>,
+}
+
+//a NodeDef flattened so nodes can be told apart by index, one tree can be shared by
+//everything using it and what each of them is up to goes in its own BehaviorState
+#[derive(Debug, Clone)]
+pub struct BehaviorTree {
+    nodes: Vec<Node>, //the root is first
+}
+
+//one agent's progress through a tree
+#[derive(Debug, Clone, Default)]
+pub struct BehaviorState {
+    time: f32,           //seconds this state has been ticked for
+    timers: Vec<f32>,    //per node, when a wait started or a cooldown is over
+    was_running: Vec<bool>,
+    is_running: Vec<bool>,
+    //the leaf (an action or wait) the last tick ended up at, the first one still running
+    //or else the last one that was ticked
+    current: Option<usize>,
+    current_running: bool,
+}
+
+impl BehaviorState {
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+}
+
+impl BehaviorTree {
+    pub fn new(def: &NodeDef) -> Self {
+        let mut tree = Self { nodes: Vec::new() };
+        tree.add(def, None);
+        tree
+    }
+
+    fn add(&mut self, def: &NodeDef, parent: Option<usize>) -> usize {
+        let (kind, children): (Kind, Vec<&NodeDef>) = match def {
+            NodeDef::Sequence(children) => (Kind::Sequence, children.iter().collect()),
+            NodeDef::Selector(children) => (Kind::Selector, children.iter().collect()),
+            NodeDef::Parallel(children) => (Kind::Parallel, children.iter().collect()),
+            NodeDef::Invert(child) => (Kind::Invert, vec![&**child]),
+            NodeDef::Succeed(child) => (Kind::Succeed, vec![&**child]),
+            NodeDef::Cooldown(seconds, child) => (Kind::Cooldown(*seconds), vec![&**child]),
+            NodeDef::Wait(seconds) => (Kind::Wait(*seconds), Vec::new()),
+            NodeDef::Condition(condition) => (Kind::Condition(*condition), Vec::new()),
+            NodeDef::Action(action) => (Kind::Action(*action), Vec::new()),
+        };
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            kind,
+            children: Vec::new(),
+            parent,
+        });
+        for child in children {
+            let child = self.add(child, Some(index));
+            self.nodes[index].children.push(child);
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    //the action a node is, None for anything else
+    pub fn action(&self, index: usize) -> Option<Action> {
+        match self.nodes.get(index)?.kind {
+            Kind::Action(action) => Some(action),
+            _ => None,
+        }
+    }
+
+    //where a node is in the tree, like "Selector > Sequence > Chase"
+    pub fn describe(&self, index: usize) -> String {
+        let mut names = Vec::new();
+        let mut node = Some(index);
+        while let Some(index) = node {
+            names.push(self.name(index));
+            node = self.nodes[index].parent;
+        }
+        names.reverse();
+        names.join(" > ")
+    }
+
+    fn name(&self, index: usize) -> String {
+        match self.nodes[index].kind {
+            Kind::Sequence => "Sequence".to_string(),
+            Kind::Selector => "Selector".to_string(),
+            Kind::Parallel => "Parallel".to_string(),
+            Kind::Invert => "Invert".to_string(),
+            Kind::Succeed => "Succeed".to_string(),
+            Kind::Cooldown(seconds) => format!("Cooldown({})", seconds),
+            Kind::Wait(seconds) => format!("Wait({})", seconds),
+            Kind::Condition(condition) => format!("{:?}", condition),
+            Kind::Action(action) => format!("{:?}", action),
+        }
+    }
+
+    //ticks the whole tree once from the root, dt is seconds since the last tick
+    pub fn tick(&self, state: &mut BehaviorState, agent: &mut impl Agent, dt: f32) -> Status {
+        if self.is_empty() {
+            return Status::Failure;
+        }
+        //a state from some other tree starts over
+        if state.timers.len() != self.nodes.len() {
+            *state = BehaviorState {
+                timers: vec![f32::NEG_INFINITY; self.nodes.len()],
+                was_running: vec![false; self.nodes.len()],
+                is_running: vec![false; self.nodes.len()],
+                ..Default::default()
+            };
+        }
+        state.time += dt;
+        std::mem::swap(&mut state.was_running, &mut state.is_running);
+        state.is_running.iter_mut().for_each(|running| *running = false);
+        state.current = None;
+        state.current_running = false;
+
+        self.tick_node(0, state, agent, dt)
+    }
+
+    fn tick_node(
+        &self,
+        index: usize,
+        state: &mut BehaviorState,
+        agent: &mut impl Agent,
+        dt: f32,
+    ) -> Status {
+        let status = self.run(index, state, agent, dt);
+        if status == Status::Running {
+            state.is_running[index] = true;
+        }
+
+        let is_leaf = matches!(self.nodes[index].kind, Kind::Action(_) | Kind::Wait(_));
+        if is_leaf && !state.current_running {
+            state.current = Some(index);
+            state.current_running = status == Status::Running;
+        }
+        status
+    }
+
+    fn run(
+        &self,
+        index: usize,
+        state: &mut BehaviorState,
+        agent: &mut impl Agent,
+        dt: f32,
+    ) -> Status {
+        let node = &self.nodes[index];
+        match node.kind {
+            Kind::Sequence => {
+                for &child in node.children.iter() {
+                    let status = self.tick_node(child, state, agent, dt);
+                    if status != Status::Success {
+                        return status;
+                    }
+                }
+                Status::Success
+            }
+            Kind::Selector => {
+                for &child in node.children.iter() {
+                    let status = self.tick_node(child, state, agent, dt);
+                    if status != Status::Failure {
+                        return status;
+                    }
+                }
+                Status::Failure
+            }
+            Kind::Parallel => {
+                let mut result = Status::Success;
+                for &child in node.children.iter() {
+                    match self.tick_node(child, state, agent, dt) {
+                        Status::Failure => result = Status::Failure,
+                        Status::Running if result == Status::Success => result = Status::Running,
+                        _ => {}
+                    }
+                }
+                result
+            }
+            Kind::Invert => match self.tick_node(node.children[0], state, agent, dt) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running,
+            },
+            Kind::Succeed => match self.tick_node(node.children[0], state, agent, dt) {
+                Status::Running => Status::Running,
+                _ => Status::Success,
+            },
+            Kind::Cooldown(seconds) => {
+                if state.time < state.timers[index] {
+                    return Status::Failure;
+                }
+                let status = self.tick_node(node.children[0], state, agent, dt);
+                if status == Status::Success {
+                    state.timers[index] = state.time + seconds;
+                }
+                status
+            }
+            Kind::Wait(seconds) => {
+                //this tick counts towards the wait too
+                if !state.was_running[index] {
+                    state.timers[index] = state.time - dt;
+                }
+                if state.time - state.timers[index] >= seconds {
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            Kind::Condition(condition) => {
+                if agent.check(condition) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            Kind::Action(action) => agent.act(action),
+        }
+    }
+}
+
+//what enemies without a behavior of their own do, patrol until they see the player and
+//then go after them, shooting whenever they can see them
+pub fn default_tree() -> NodeDef {
+    NodeDef::Selector(vec![
+        NodeDef::Sequence(vec![
+            NodeDef::Condition(Condition::RemembersPlayer),
+            NodeDef::Parallel(vec![
+                NodeDef::Action(Action::Chase),
+                NodeDef::Succeed(Box::new(NodeDef::Sequence(vec![
+                    NodeDef::Condition(Condition::SeesPlayer),
+                    NodeDef::Action(Action::Shoot),
+                ]))),
+            ]),
+        ]),
+        NodeDef::Action(Action::Patrol),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.25;
+
+    //answers conditions from sees and actions from the statuses it was given, and keeps
+    //track of what the tree asked it to do
+    struct FakeAgent {
+        sees: bool,
+        chase: Status,
+        shoot: Status,
+        acted: Vec<Action>,
+    }
+
+    impl FakeAgent {
+        fn new() -> Self {
+            Self {
+                sees: true,
+                chase: Status::Running,
+                shoot: Status::Success,
+                acted: Vec::new(),
+            }
+        }
+    }
+
+    impl Agent for FakeAgent {
+        fn check(&mut self, condition: Condition) -> bool {
+            condition == Condition::SeesPlayer && self.sees
+        }
+
+        fn act(&mut self, action: Action) -> Status {
+            self.acted.push(action);
+            match action {
+                Action::Patrol => Status::Running,
+                Action::Chase => self.chase,
+                Action::Shoot => self.shoot,
+            }
+        }
+    }
+
+    fn tree(def: NodeDef) -> (BehaviorTree, BehaviorState) {
+        (BehaviorTree::new(&def), BehaviorState::default())
+    }
+
+    #[test]
+    fn sequence_stops_at_the_first_child_that_doesnt_succeed() {
+        let (tree, mut state) = tree(NodeDef::Sequence(vec![
+            NodeDef::Condition(Condition::SeesPlayer),
+            NodeDef::Action(Action::Shoot),
+        ]));
+        let mut agent = FakeAgent::new();
+        agent.sees = false;
+
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Failure);
+        assert!(agent.acted.is_empty());
+
+        agent.sees = true;
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Success);
+        assert_eq!(agent.acted, vec![Action::Shoot]);
+    }
+
+    #[test]
+    fn selector_stops_at_the_first_child_that_doesnt_fail() {
+        let (tree, mut state) = tree(NodeDef::Selector(vec![
+            NodeDef::Action(Action::Chase),
+            NodeDef::Action(Action::Patrol),
+        ]));
+        let mut agent = FakeAgent::new();
+
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Running);
+        assert_eq!(agent.acted, vec![Action::Chase]);
+        assert_eq!(tree.action(state.current().unwrap()), Some(Action::Chase));
+
+        agent.chase = Status::Failure;
+        agent.acted.clear();
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Running);
+        assert_eq!(agent.acted, vec![Action::Chase, Action::Patrol]);
+        assert_eq!(tree.action(state.current().unwrap()), Some(Action::Patrol));
+    }
+
+    #[test]
+    fn parallel_fails_when_one_child_fails_even_if_another_is_running() {
+        let (tree, mut state) = tree(NodeDef::Parallel(vec![
+            NodeDef::Action(Action::Chase),
+            NodeDef::Action(Action::Shoot),
+        ]));
+        let mut agent = FakeAgent::new();
+
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Running);
+
+        agent.shoot = Status::Failure;
+        agent.acted.clear();
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Failure);
+        //every child still got ticked
+        assert_eq!(agent.acted, vec![Action::Chase, Action::Shoot]);
+
+        agent.chase = Status::Success;
+        agent.shoot = Status::Success;
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Success);
+    }
+
+    #[test]
+    fn cooldown_fails_without_ticking_its_child_until_the_time_is_up() {
+        let (tree, mut state) = tree(NodeDef::Cooldown(
+            1.0,
+            Box::new(NodeDef::Action(Action::Shoot)),
+        ));
+        let mut agent = FakeAgent::new();
+
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Success);
+        for _ in 0..3 {
+            assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Failure);
+        }
+        assert_eq!(agent.acted.len(), 1);
+
+        //a full second after the shot
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Success);
+        assert_eq!(agent.acted.len(), 2);
+    }
+
+    #[test]
+    fn cooldown_doesnt_start_while_the_child_fails() {
+        let (tree, mut state) = tree(NodeDef::Cooldown(
+            1.0,
+            Box::new(NodeDef::Action(Action::Shoot)),
+        ));
+        let mut agent = FakeAgent::new();
+        agent.shoot = Status::Failure;
+
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Failure);
+        agent.shoot = Status::Success;
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Success);
+    }
+
+    #[test]
+    fn wait_starts_over_after_a_tick_it_didnt_run() {
+        let (tree, mut state) = tree(NodeDef::Sequence(vec![
+            NodeDef::Condition(Condition::SeesPlayer),
+            NodeDef::Wait(1.0),
+        ]));
+        let mut agent = FakeAgent::new();
+
+        for _ in 0..2 {
+            assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Running);
+        }
+        agent.sees = false;
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Failure);
+
+        //another whole second from here, not what was left of the first one
+        agent.sees = true;
+        for _ in 0..3 {
+            assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Running);
+        }
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Success);
+        //and again from the start after finishing
+        assert_eq!(tree.tick(&mut state, &mut agent, DT), Status::Running);
+    }
+
+    #[test]
+    fn describe_names_the_path_from_the_root() {
+        let tree = BehaviorTree::new(&default_tree());
+        let chase = (0..tree.len())
+            .find(|&index| tree.action(index) == Some(Action::Chase))
+            .unwrap();
+        assert_eq!(tree.describe(chase), "Selector > Sequence > Parallel > Chase");
+    }
+}
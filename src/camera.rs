@@ -1,6 +1,12 @@
-use cgmath::{ortho, Matrix4, Point3, Vector3};
+use cgmath::{
+    ortho, point2, vec2, vec4, InnerSpace, Matrix4, Point2, Point3, SquareMatrix, Vector2,
+    Vector3,
+};
 use wgpu::util::DeviceExt;
 
+use crate::physics::Aabb;
+use crate::settings::{ScalingMode, Settings};
+
 #[rustfmt::skip]
 const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
@@ -9,49 +15,200 @@ const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct CameraUniform {
-    //view_matrix: Matrix4<f32>,
-    //projection_matrix: Matrix4<f32>,
-    view_proj: [[f32; 4]; 4],
+//what part of the world is on screen, kept apart from the gpu side of the camera
+//so it can be worked with without a device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraView {
+    pub center: Point2<f32>,
+    pub size: Vector2<f32>, //world units from the left edge of the screen to the right and bottom to top
+    pub zoom: f32,          //2.0 shows half as much of the world in each direction
+    pub rotation: f32,      //radians the camera is turned counter clockwise
 }
 
-impl CameraUniform {
-    fn new(config: &wgpu::SurfaceConfiguration) -> Self {
-        let left = 0.0;
-        let right = config.width as f32;
-        let bottom = 0.0;
-        let top = config.height as f32;
+impl CameraView {
+    //world units actually on screen once zoom is applied
+    pub fn visible_size(&self) -> Vector2<f32> {
+        self.size / self.zoom
+    }
+
+    pub fn view_proj(&self) -> Matrix4<f32> {
+        let half = self.visible_size() / 2.0;
+        let left = -half.x;
+        let right = half.x;
+        let bottom = -half.y;
+        let top = half.y;
         let near = 0.1;
         let far = 100.0;
         let projection_matrix = ortho(left, right, bottom, top, near, far);
 
-        let eye = Point3::new(0.0, 0.0, 1.0);
-        let target = Point3::new(0.0, 0.0, 0.0);
-        let up = Vector3::new(0.0, 1.0, 0.0);
+        let eye = Point3::new(self.center.x, self.center.y, 1.0);
+        let target = Point3::new(self.center.x, self.center.y, 0.0);
+        //turning the up vector turns the world the other way on screen
+        let up = Vector3::new(-self.rotation.sin(), self.rotation.cos(), 0.0);
         let view_matrix = Matrix4::look_at_rh(eye, target, up);
 
-        let view_proj_mat = OPENGL_TO_WGPU_MATRIX * projection_matrix * view_matrix;
+        OPENGL_TO_WGPU_MATRIX * projection_matrix * view_matrix
+    }
+
+    //screen is in logical pixels from the top left of the window (what winit reports divided
+    //by the scale factor), the result is the point on the z = 0 plane everything is drawn on
+    pub fn screen_to_world(
+        &self,
+        screen: Point2<f32>,
+        viewport: &Viewport,
+        scale_factor: f32,
+    ) -> Point2<f32> {
+        let physical = screen * scale_factor;
+        let ndc_x = (physical.x - viewport.x) / viewport.width * 2.0 - 1.0;
+        //pixels go down the screen, clip space goes up
+        let ndc_y = 1.0 - (physical.y - viewport.y) / viewport.height * 2.0;
+
+        //depth doesn't matter for an orthographic projection but it has to be the one of the
+        //world plane or the inverse lands somewhere in front of it
+        let view_proj = self.view_proj();
+        let depth = view_proj * vec4(self.center.x, self.center.y, 0.0, 1.0);
+        let ndc_z = depth.z / depth.w;
+
+        let inverse = match view_proj.invert() {
+            Some(inverse) => inverse,
+            None => return self.center,
+        };
+        let world = inverse * vec4(ndc_x, ndc_y, ndc_z, 1.0);
+        point2::<f32>(world.x / world.w, world.y / world.w)
+    }
 
+    //opposite of screen_to_world, in logical pixels from the top left of the window
+    pub fn world_to_screen(
+        &self,
+        world: Point2<f32>,
+        viewport: &Viewport,
+        scale_factor: f32,
+    ) -> Point2<f32> {
+        let clip = self.view_proj() * vec4(world.x, world.y, 0.0, 1.0);
+        let ndc = vec2::<f32>(clip.x / clip.w, clip.y / clip.w);
+        let physical = point2::<f32>(
+            viewport.x + (ndc.x + 1.0) / 2.0 * viewport.width,
+            viewport.y + (1.0 - ndc.y) / 2.0 * viewport.height,
+        );
+        physical / scale_factor
+    }
+}
+
+//part of the window the world is drawn into, in physical pixels from the top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+//a kick in one direction that eases back over its duration
+struct Punch {
+    offset: Vector2<f32>,
+    duration: f32,
+    elapsed: f32,
+}
+
+//smooth noise between -1 and 1, the same seed and time always give the same value
+fn noise(seed: u32, time: f32) -> f32 {
+    let hash = |i: i32| {
+        let mut x = (i as u32).wrapping_mul(0x9e37_79b9) ^ seed.wrapping_mul(0x85eb_ca6b);
+        x ^= x >> 15;
+        x = x.wrapping_mul(0x2c1b_3c6d);
+        x ^= x >> 12;
+        x as f32 / u32::MAX as f32 * 2.0 - 1.0
+    };
+    let i = time.floor();
+    let t = time - i;
+    let t = t * t * (3.0 - 2.0 * t);
+    let a = hash(i as i32);
+    let b = hash(i as i32 + 1);
+    a + (b - a) * t
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraUniform {
+    //view_matrix: Matrix4<f32>,
+    //projection_matrix: Matrix4<f32>,
+    view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    fn new(view: &CameraView) -> Self {
         Self {
             //view_matrix,
             //projection_matrix,
-            view_proj: view_proj_mat.into(),
+            view_proj: view.view_proj().into(),
         }
     }
 }
 
 pub struct Camera {
-    //camera_uniform: CameraUniform,
-    //camera_buffer: wgpu::Buffer,
+    //world point in the middle of the screen
+    pub position: Point2<f32>,
+    //the followed target can move this far (in world units) from the middle of the screen
+    //in each direction before the camera starts moving
+    pub deadzone: Vector2<f32>,
+    //how quickly the camera catches up with where it wants to be, higher is snappier
+    pub smoothing: f32,
+    //the camera never shows anything outside of these
+    pub bounds: Option<Aabb>,
+    //zoom never goes past these, min is the furthest out
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    //same as smoothing but for zoom changes
+    pub zoom_smoothing: f32,
+    //accessibility setting, when off shakes and punches are ignored
+    pub shake_enabled: bool,
+    //how far (world units) and how much (radians) the camera moves at full trauma
+    pub max_shake_offset: Vector2<f32>,
+    pub max_shake_angle: f32,
+    //trauma lost per second
+    pub trauma_decay: f32,
+    //how fast the shake wobbles, higher is more jittery
+    pub shake_frequency: f32,
+
+    trauma: f32,
+    shake_time: f32,
+    shake_offset: Vector2<f32>,
+    shake_angle: f32,
+    punches: Vec<Punch>,
+
+    zoom: f32,
+    zoom_goal: f32,
+    goal: Point2<f32>,
+    view_size: Vector2<f32>,
+    viewport: Viewport,
+    scaling: ScalingMode,
+    virtual_size: Vector2<f32>,
+
+    camera_uniform: CameraUniform,
+    camera_buffer: wgpu::Buffer,
     camera_bind_group_layout: wgpu::BindGroupLayout,
     camera_bind_group: wgpu::BindGroup,
 }
 
 impl Camera {
-    pub fn new(config: &wgpu::SurfaceConfiguration, device: &wgpu::Device) -> Self {
-        let camera_uniform = CameraUniform::new(config);
+    pub fn new(
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        position: Point2<f32>,
+        settings: &Settings,
+    ) -> Self {
+        let virtual_size = vec2::<f32>(
+            settings.virtual_resolution.0 as f32,
+            settings.virtual_resolution.1 as f32,
+        );
+        let (view_size, viewport) =
+            Self::fit(settings.scaling, virtual_size, config.width, config.height);
+        let camera_uniform = CameraUniform::new(&CameraView {
+            center: position,
+            size: view_size,
+            zoom: 1.0,
+            rotation: 0.0,
+        });
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera buffer"),
             contents: bytemuck::cast_slice(&[camera_uniform]),
@@ -83,13 +240,229 @@ impl Camera {
         });
 
         Self {
-            //camera_uniform,
-            //camera_buffer,
+            position,
+            deadzone: vec2::<f32>(75.0, 50.0),
+            smoothing: 8.0,
+            bounds: None,
+            min_zoom: 0.5,
+            max_zoom: 4.0,
+            zoom_smoothing: 10.0,
+            shake_enabled: settings.screen_shake,
+            max_shake_offset: vec2::<f32>(24.0, 18.0),
+            max_shake_angle: 0.05,
+            trauma_decay: 1.5,
+            shake_frequency: 20.0,
+            trauma: 0.0,
+            shake_time: 0.0,
+            shake_offset: vec2::<f32>(0.0, 0.0),
+            shake_angle: 0.0,
+            punches: Vec::new(),
+            zoom: 1.0,
+            zoom_goal: 1.0,
+            goal: position,
+            view_size,
+            viewport,
+            scaling: settings.scaling,
+            virtual_size,
+            camera_uniform,
+            camera_buffer,
             camera_bind_group_layout,
             camera_bind_group,
         }
     }
 
+    //how much of the world fits in a window of the given size and where in the window it goes
+    fn fit(
+        scaling: ScalingMode,
+        virtual_size: Vector2<f32>,
+        width: u32,
+        height: u32,
+    ) -> (Vector2<f32>, Viewport) {
+        let window = vec2::<f32>(width as f32, height as f32);
+        let fit = (window.x / virtual_size.x).min(window.y / virtual_size.y);
+        let scale = match scaling {
+            ScalingMode::Window => {
+                let viewport = Viewport {
+                    x: 0.0,
+                    y: 0.0,
+                    width: window.x,
+                    height: window.y,
+                };
+                return (window, viewport);
+            }
+            ScalingMode::Letterbox => fit,
+            //a window smaller than the virtual resolution can't fit even 1x, so shrink then
+            ScalingMode::IntegerScale if fit >= 1.0 => fit.floor(),
+            ScalingMode::IntegerScale => fit,
+        };
+
+        let size = virtual_size * scale;
+        let viewport = Viewport {
+            x: ((window.x - size.x) / 2.0).floor(),
+            y: ((window.y - size.y) / 2.0).floor(),
+            width: size.x,
+            height: size.y,
+        };
+        (virtual_size, viewport)
+    }
+
+    //recomputes the projection for a new window size, in physical pixels
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (view_size, viewport) = Self::fit(self.scaling, self.virtual_size, width, height);
+        self.view_size = view_size;
+        self.viewport = viewport;
+        self.goal = self.clamp_to_bounds(self.goal);
+        self.position = self.clamp_to_bounds(self.position);
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    //where the camera is looking including shake and punches
+    pub fn view(&self) -> CameraView {
+        let punch = self
+            .punches
+            .iter()
+            .map(|punch| {
+                let left = 1.0 - punch.elapsed / punch.duration;
+                punch.offset * left * left
+            })
+            .fold(vec2::<f32>(0.0, 0.0), |sum, offset| sum + offset);
+        CameraView {
+            center: self.position + self.shake_offset + punch,
+            size: self.view_size,
+            zoom: self.zoom,
+            rotation: self.shake_angle,
+        }
+    }
+
+    //amount is added to the current trauma (0..1), shake grows with trauma squared so
+    //small hits barely move the camera and big ones really throw it around
+    pub fn add_trauma(&mut self, amount: f32) {
+        if self.shake_enabled {
+            self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    //knocks the camera strength world units towards direction, easing back over duration seconds
+    pub fn punch(&mut self, direction: Vector2<f32>, strength: f32, duration: f32) {
+        if !self.shake_enabled || duration <= 0.0 || direction == vec2::<f32>(0.0, 0.0) {
+            return;
+        }
+        self.punches.push(Punch {
+            offset: direction.normalize() * strength,
+            duration,
+            elapsed: 0.0,
+        });
+    }
+
+    //advances shake and punches, dt is in seconds
+    pub fn update_effects(&mut self, dt: f32) {
+        self.trauma = (self.trauma - self.trauma_decay * dt).max(0.0);
+        self.shake_time += dt;
+
+        let shake = self.trauma * self.trauma;
+        let time = self.shake_time * self.shake_frequency;
+        self.shake_offset = vec2::<f32>(
+            self.max_shake_offset.x * shake * noise(0, time),
+            self.max_shake_offset.y * shake * noise(1, time),
+        );
+        self.shake_angle = self.max_shake_angle * shake * noise(2, time);
+
+        for punch in self.punches.iter_mut() {
+            punch.elapsed += dt;
+        }
+        self.punches.retain(|punch| punch.elapsed < punch.duration);
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    //eases towards the new zoom over the next frames, clamped to the zoom limits
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom_goal = zoom.clamp(self.min_zoom, self.max_zoom);
+    }
+
+    //multiplies the zoom being eased towards, so repeated steps feel even in and out
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.set_zoom(self.zoom_goal * factor);
+    }
+
+    pub fn screen_to_world(&self, screen: Point2<f32>, scale_factor: f32) -> Point2<f32> {
+        self.view().screen_to_world(screen, &self.viewport, scale_factor)
+    }
+
+    pub fn world_to_screen(&self, world: Point2<f32>, scale_factor: f32) -> Point2<f32> {
+        self.view().world_to_screen(world, &self.viewport, scale_factor)
+    }
+
+    //jumps straight to the target, for the first frame or after a respawn
+    pub fn snap_to(&mut self, target: Point2<f32>) {
+        self.goal = self.clamp_to_bounds(target);
+        self.position = self.goal;
+    }
+
+    //moves towards target once it leaves the deadzone and eases the zoom, dt is in seconds
+    pub fn follow(&mut self, target: Point2<f32>, dt: f32) {
+        let t = 1.0 - (-self.zoom_smoothing * dt).exp();
+        self.zoom += (self.zoom_goal - self.zoom) * t;
+        if (self.zoom_goal - self.zoom).abs() < 0.001 {
+            self.zoom = self.zoom_goal;
+        }
+
+        let offset = target - self.goal;
+        if offset.x.abs() > self.deadzone.x {
+            self.goal.x = target.x - self.deadzone.x * offset.x.signum();
+        }
+        if offset.y.abs() > self.deadzone.y {
+            self.goal.y = target.y - self.deadzone.y * offset.y.signum();
+        }
+        self.goal = self.clamp_to_bounds(self.goal);
+
+        //exponential smoothing so the result doesn't depend on the frame rate
+        let t = 1.0 - (-self.smoothing * dt).exp();
+        self.position += (self.goal - self.position) * t;
+        self.position = self.clamp_to_bounds(self.position);
+    }
+
+    //keeps the whole screen inside the bounds, a level smaller than the screen sticks to
+    //the bottom left corner like the game looked before the camera could move
+    fn clamp_to_bounds(&self, center: Point2<f32>) -> Point2<f32> {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return center,
+        };
+        let half = self.view().visible_size() / 2.0;
+        let clamp_axis = |value: f32, min: f32, max: f32, half: f32| {
+            if max - min <= half * 2.0 {
+                min + half
+            } else {
+                value.clamp(min + half, max - half)
+            }
+        };
+        let max = bounds.max();
+        point2::<f32>(
+            clamp_axis(center.x, bounds.pos.x, max.x, half.x),
+            clamp_axis(center.y, bounds.pos.y, max.y, half.y),
+        )
+    }
+
+    //writes the current view to the uniform buffer, once per frame before drawing
+    pub fn update_buffer(&mut self, queue: &wgpu::Queue) {
+        self.camera_uniform = CameraUniform::new(&self.view());
+        queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+    }
+
     pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
         &self.camera_bind_group_layout
     }
@@ -98,3 +471,118 @@ impl Camera {
         &self.camera_bind_group
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.001;
+
+    fn view(zoom: f32) -> CameraView {
+        CameraView {
+            center: point2::<f32>(400.0, 300.0),
+            size: vec2::<f32>(800.0, 600.0),
+            zoom,
+            rotation: 0.0,
+        }
+    }
+
+    fn viewport(x: f32, y: f32, width: f32, height: f32) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn assert_near(a: Point2<f32>, b: Point2<f32>) {
+        assert!(
+            (a.x - b.x).abs() < EPSILON && (a.y - b.y).abs() < EPSILON,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn screen_center_is_camera_center() {
+        let viewport = viewport(0.0, 0.0, 800.0, 600.0);
+        let world = view(1.0).screen_to_world(point2(400.0, 300.0), &viewport, 1.0);
+        assert_near(world, point2(400.0, 300.0));
+    }
+
+    #[test]
+    fn screen_y_points_down_and_world_y_up() {
+        let viewport = viewport(0.0, 0.0, 800.0, 600.0);
+        let top_left = view(1.0).screen_to_world(point2(0.0, 0.0), &viewport, 1.0);
+        assert_near(top_left, point2(0.0, 600.0));
+        let bottom_right = view(1.0).screen_to_world(point2(800.0, 600.0), &viewport, 1.0);
+        assert_near(bottom_right, point2(800.0, 0.0));
+    }
+
+    #[test]
+    fn zoom_shrinks_visible_area_around_center() {
+        let viewport = viewport(0.0, 0.0, 800.0, 600.0);
+        let top_left = view(2.0).screen_to_world(point2(0.0, 0.0), &viewport, 1.0);
+        assert_near(top_left, point2(200.0, 450.0));
+        assert_eq!(view(2.0).visible_size(), vec2(400.0, 300.0));
+    }
+
+    #[test]
+    fn scale_factor_converts_logical_pixels() {
+        //a hidpi window has twice the physical pixels, the cursor in logical pixels still
+        //lands on the same world point
+        let viewport = viewport(0.0, 0.0, 1600.0, 1200.0);
+        let world = view(1.0).screen_to_world(point2(200.0, 150.0), &viewport, 2.0);
+        assert_near(world, point2(200.0, 450.0));
+    }
+
+    #[test]
+    fn letterbox_offset_is_skipped() {
+        //window is 1000 wide, the 800x600 viewport sits 100 pixels in from the left
+        let viewport = viewport(100.0, 0.0, 800.0, 600.0);
+        let world = view(1.0).screen_to_world(point2(100.0, 600.0), &viewport, 1.0);
+        assert_near(world, point2(0.0, 0.0));
+    }
+
+    #[test]
+    fn rotation_turns_the_world_on_screen() {
+        //turning the camera a quarter counter clockwise turns the world clockwise on screen,
+        //what was to the right of the center ends up below it
+        let viewport = viewport(0.0, 0.0, 800.0, 600.0);
+        let rotated = CameraView {
+            rotation: std::f32::consts::FRAC_PI_2,
+            ..view(1.0)
+        };
+        let screen = rotated.world_to_screen(point2(500.0, 300.0), &viewport, 1.0);
+        assert_near(screen, point2(400.0, 400.0));
+    }
+
+    #[test]
+    fn noise_is_smooth_and_bounded() {
+        for step in 0..1000 {
+            let time = step as f32 * 0.01;
+            let value = noise(3, time);
+            assert!((-1.0..=1.0).contains(&value));
+            assert!((noise(3, time + 0.001) - value).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn world_to_screen_round_trips() {
+        let viewport = viewport(37.0, 12.0, 960.0, 720.0);
+        for zoom in [0.5, 1.0, 1.7, 4.0] {
+            for scale_factor in [1.0, 1.25, 2.0] {
+                let view = CameraView {
+                    rotation: 0.03,
+                    ..view(zoom)
+                };
+                let world = point2::<f32>(123.0, 456.0);
+                let screen = view.world_to_screen(world, &viewport, scale_factor);
+                let back = view.screen_to_world(screen, &viewport, scale_factor);
+                assert_near(back, world);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+//why a source failed to parse. position is the line and column (starting at 1) the problem
+//is at, there's none when it's about the whole thing like a level without a spawn
+#[derive(Debug)]
+pub struct ParseError {
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl ParseError {
+    //row and column are counted from 0 like the indices they usually come from
+    pub(crate) fn new(row: usize, column: usize, message: &str) -> Self {
+        Self {
+            position: Some((row + 1, column + 1)),
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn invalid(message: &str) -> Self {
+        Self {
+            position: None,
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn in_file(self, path: &Path) -> DataError {
+        let path = path.to_path_buf();
+        match self.position {
+            Some((line, column)) => DataError::Parse {
+                path,
+                line,
+                column,
+                message: self.message,
+            },
+            None => DataError::Invalid {
+                path,
+                message: self.message,
+            },
+        }
+    }
+}
+
+//a file the game loads (levels, weapons, enemies, ...) that couldn't be used
+#[derive(Debug)]
+pub enum DataError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    //parsed fine but something in it can't work
+    Invalid {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataError::Io { path, source } => {
+                write!(f, "couldn't read {}: {}", path.display(), source)
+            }
+            DataError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            DataError::Invalid { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for DataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DataError::Io { source, .. } => Some(source),
+            DataError::Parse { .. } | DataError::Invalid { .. } => None,
+        }
+    }
+}
+
+pub fn from_ron<T: DeserializeOwned>(source: &str) -> Result<T, ParseError> {
+    ron::from_str(source).map_err(|err| ParseError {
+        position: Some((err.position.line, err.position.col)),
+        message: err.code.to_string(),
+    })
+}
+
+pub fn read(path: &Path) -> Result<String, DataError> {
+    fs::read_to_string(path).map_err(|source| DataError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+pub fn load_ron<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, DataError> {
+    let path = path.as_ref();
+    let source = read(path)?;
+    from_ron(&source).map_err(|err| err.in_file(path))
+}
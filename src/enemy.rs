@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use cgmath::{point2, vec2, InnerSpace, Point2, Vector2};
+use serde::Deserialize;
+
+use crate::behavior::{self, Action, Agent, BehaviorState, BehaviorTree, Condition, NodeDef, Status};
+use crate::data::{self, DataError};
+use crate::health::Health;
+use crate::navigation::{Link, LinkKind, NavGraph};
+use crate::physics::{ray_cast, Aabb};
+use crate::weapon::{Weapon, WeaponDef};
+use crate::world::{Entity, Facing, GRAVITY, MAX_FALL_SPEED, QUAD_SIZE};
+
+//an enemy type as it's written in assets/enemies.ron, levels pick one with the
+//entity's "type" property
+//
+//{
+//    "grunt": (
+//        health: 30.0,
+//        speed: 80.0,
+//        chase_speed: 160.0,
+//        sight_range: 400.0,
+//        contact_damage: 10.0,
+//        behavior: Selector([
+//            Sequence([Condition(RemembersPlayer), Action(Chase)]),
+//            Action(Patrol),
+//        ]),
+//    ),
+//}
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyDef {
+    pub health: f32,
+    pub speed: f32,          //pixels per second while patrolling
+    pub chase_speed: f32,    //pixels per second while going after the player
+    pub sight_range: f32,    //pixels, the player has to be closer than this to be seen
+    pub contact_damage: f32, //what touching the player does to them
+    //seconds it keeps going after the player once it lost sight of them
+    #[serde(default = "default_memory")]
+    pub memory: f32,
+    //enemies without a weapon only hurt by touching
+    #[serde(default)]
+    pub weapon: Option<WeaponDef>,
+    //what it does, see behavior::NodeDef
+    #[serde(default = "behavior::default_tree")]
+    pub behavior: NodeDef,
+}
+
+fn default_memory() -> f32 {
+    5.0
+}
+
+pub type EnemyTypes = HashMap<String, EnemyDef>;
+
+//how close (pixels) to a jump link's takeoff point is close enough to jump
+const JUMP_TOLERANCE: f32 = 4.0;
+
+//the enemy types in path, rejected as a whole when a type's weapon doesn't check out
+pub fn load_types(path: impl AsRef<Path>) -> Result<EnemyTypes, DataError> {
+    let path = path.as_ref();
+    let types: EnemyTypes = data::load_ron(path)?;
+    for weapon in types.values().filter_map(|def| def.weapon.as_ref()) {
+        weapon.check(path)?;
+    }
+    Ok(types)
+}
+
+//the type enemies without a "type" property get
+pub const DEFAULT_TYPE: &str = "grunt";
+
+//what's used when assets/enemies.ron can't be loaded
+pub fn default_types() -> EnemyTypes {
+    let mut types = EnemyTypes::new();
+    types.insert(
+        DEFAULT_TYPE.to_string(),
+        EnemyDef {
+            health: 30.0,
+            speed: 80.0,
+            chase_speed: 160.0,
+            sight_range: 400.0,
+            contact_damage: 10.0,
+            memory: default_memory(),
+            weapon: None,
+            behavior: behavior::default_tree(),
+        },
+    );
+    types
+}
+
+pub struct Enemy {
+    id: usize,
+    pub pos: Point2<f32>,
+    pub prev_pos: Point2<f32>, //position before the last tick, used for interpolation
+    pub vel: Vector2<f32>,     //pixels per second
+    //pixels per second it walks on its own, kept in the air. vel.x is made from this and
+    //the knockback every tick so the two don't pile up
+    pub walk: f32,
+    pub knockback: Vector2<f32>,
+    pub is_grounded: bool,
+    pub hit_wall: bool, //walked into the side of a block last tick
+    pub facing: Facing,
+    tree: Arc<BehaviorTree>,
+    pub brain: BehaviorState, //where it is in its behavior tree
+    pub health: Health,
+    pub weapon: Option<Weapon>,
+    pub path: Vec<Link>,     //what's left of the way to the player, first link is next
+    jump_to: Option<f32>,    //x it's steering towards while in the air from a jump link
+    last_seen: Point2<f32>,  //where the player's feet were when it last saw them
+    since_seen: f32,         //seconds
+
+    pub speed: f32,
+    pub chase_speed: f32,
+    pub sight_range: f32,
+    pub contact_damage: f32,
+    pub memory: f32,         //seconds it keeps chasing after losing sight of the player
+    pub gravity: f32,        //pixels per second squared
+    pub max_fall_speed: f32, //pixels per second
+}
+
+impl Enemy {
+    //tree has to be built from def.behavior, it's passed in so enemies of a type can
+    //share it
+    pub fn new(id: usize, pos: Point2<f32>, def: &EnemyDef, tree: Arc<BehaviorTree>) -> Self {
+        Self {
+            id,
+            pos,
+            prev_pos: pos,
+            vel: vec2::<f32>(0.0, 0.0),
+            walk: 0.0,
+            knockback: vec2::<f32>(0.0, 0.0),
+            is_grounded: false,
+            hit_wall: false,
+            facing: Facing::Left,
+            tree,
+            brain: BehaviorState::default(),
+            health: Health::new(def.health, 0.0),
+            weapon: def.weapon.clone().map(Weapon::new),
+            path: Vec::new(),
+            jump_to: None,
+            last_seen: pos,
+            since_seen: f32::INFINITY,
+            speed: def.speed,
+            chase_speed: def.chase_speed,
+            sight_range: def.sight_range,
+            contact_damage: def.contact_damage,
+            memory: def.memory,
+            gravity: GRAVITY,
+            max_fall_speed: MAX_FALL_SPEED,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.pos, vec2::<f32>(QUAD_SIZE, QUAD_SIZE))
+    }
+
+    pub fn tree(&self) -> &BehaviorTree {
+        &self.tree
+    }
+
+    //bottom middle, what the navigation graph goes by
+    pub fn feet(&self) -> Point2<f32> {
+        point2::<f32>(self.pos.x + QUAD_SIZE / 2.0, self.pos.y)
+    }
+
+    //close enough and no block in between
+    pub fn can_see(&self, target: &Aabb, blocks: &[Aabb]) -> bool {
+        let eye = self.aabb().center();
+        let offset = target.center() - eye;
+        if offset.magnitude() > self.sight_range {
+            return false;
+        }
+        //the ray is the whole offset long so anything hit before 1 is in the way
+        !blocks
+            .iter()
+            .any(|block| ray_cast(eye, offset, block).is_some_and(|hit| hit < 1.0))
+    }
+
+    //true when there's something to stand on right past the front foot
+    fn ground_ahead(&self, direction: f32, blocks: &[Aabb]) -> bool {
+        let aabb = self.aabb();
+        let x = if direction > 0.0 {
+            aabb.max().x + 1.0
+        } else {
+            aabb.pos.x - 1.0
+        };
+        let probe = Aabb::new(point2::<f32>(x - 0.5, aabb.pos.y - 2.0), vec2::<f32>(1.0, 1.0));
+        blocks.iter().any(|block| block.overlaps(&probe))
+    }
+
+    //ticks the behavior tree, which sets how fast to walk (or jump) this tick. returns
+    //the direction to shoot in when the tree fired the weapon
+    pub fn think(
+        &mut self,
+        player: &Aabb,
+        blocks: &[Aabb],
+        navigation: &NavGraph,
+        dt: f32,
+    ) -> Option<Vector2<f32>> {
+        let sees = self.can_see(player, blocks);
+        if sees {
+            self.last_seen = point2::<f32>(player.center().x, player.pos.y);
+            self.since_seen = 0.0;
+        } else {
+            self.since_seen += dt;
+        }
+
+        if let Some(weapon) = self.weapon.as_mut() {
+            weapon.update(dt);
+        }
+
+        //standing still unless an action says otherwise, in the air it keeps walking the
+        //way it was, knockback is added on top by the world and never carried in walk
+        if self.is_grounded {
+            self.walk = 0.0;
+        }
+
+        let tree = Arc::clone(&self.tree);
+        let mut brain = std::mem::take(&mut self.brain);
+        let mut agent = EnemyAgent {
+            enemy: self,
+            player,
+            blocks,
+            navigation,
+            sees,
+            shot: None,
+        };
+        tree.tick(&mut brain, &mut agent, dt);
+        let shot = agent.shot;
+        self.brain = brain;
+        shot
+    }
+
+    //turns around at walls and edges
+    fn patrol(&mut self, blocks: &[Aabb]) {
+        self.path.clear();
+        self.jump_to = None;
+        //in the air there's nothing to decide, it keeps going
+        if self.is_grounded && (self.hit_wall || !self.ground_ahead(self.facing.sign(), blocks)) {
+            self.facing = match self.facing {
+                Facing::Left => Facing::Right,
+                Facing::Right => Facing::Left,
+            };
+        }
+        self.walk = self.facing.sign() * self.speed;
+    }
+
+    //follows the navigation graph to the platform under target, or walks straight at it
+    //when it's on the same one or there's no way there. true once it's there
+    fn go_to(&mut self, target: Point2<f32>, navigation: &NavGraph, blocks: &[Aabb]) -> bool {
+        if self.is_grounded {
+            self.jump_to = None;
+        } else {
+            //a jump has to be steered to land where the link said, falling off an edge
+            //just keeps going the way it walked off
+            if let Some(x) = self.jump_to {
+                self.walk_towards(x, navigation.arc.speed);
+            }
+            return false;
+        }
+
+        let here = navigation.platform_at(self.feet());
+        let goal = navigation.platform_below(target);
+        let (here, goal) = match (here, goal) {
+            (Some(here), Some(goal)) if here != goal => (here, goal),
+            _ => {
+                self.path.clear();
+                return self.chase_directly(target, blocks);
+            }
+        };
+
+        let stale = self.path.first().map(|link| link.from) != Some(here)
+            || self.path.last().map(|link| link.to) != Some(goal);
+        if stale {
+            self.path = navigation.find_path(here, goal).unwrap_or_default();
+        }
+        let link = match self.path.first() {
+            Some(link) => *link,
+            None => return self.chase_directly(target, blocks),
+        };
+
+        match link.kind {
+            //both start at an edge, walking over it gets there
+            LinkKind::Walk | LinkKind::Drop => {
+                let center = navigation.platforms[here].center().x;
+                let edge = if link.from_x < center { -1.0 } else { 1.0 };
+                self.walk_towards(link.from_x + edge * QUAD_SIZE, self.chase_speed);
+            }
+            LinkKind::Jump => {
+                let x = self.feet().x;
+                if (link.from_x - x).abs() <= JUMP_TOLERANCE {
+                    self.vel.y = navigation.arc.jump_impulse;
+                    self.jump_to = Some(link.to_x);
+                    self.walk_towards(link.to_x, navigation.arc.speed);
+                } else {
+                    self.walk_towards(link.from_x, self.chase_speed);
+                }
+            }
+        }
+        false
+    }
+
+    //waits at the edge instead of following the target off it, and stops once it's right
+    //under or over it, which is as close as it gets so it counts as being there
+    fn chase_directly(&mut self, target: Point2<f32>, blocks: &[Aabb]) -> bool {
+        let offset = target.x - self.feet().x;
+        self.facing = if offset < 0.0 {
+            Facing::Left
+        } else {
+            Facing::Right
+        };
+        let close = offset.abs() < QUAD_SIZE / 2.0;
+        let blocked = self.is_grounded && !self.ground_ahead(self.facing.sign(), blocks);
+        self.walk = if close || blocked {
+            0.0
+        } else {
+            self.facing.sign() * self.chase_speed
+        };
+        close
+    }
+
+    //walks its feet towards x without overshooting it by more than a tick
+    fn walk_towards(&mut self, x: f32, speed: f32) {
+        let offset = x - self.feet().x;
+        if offset.abs() <= JUMP_TOLERANCE / 2.0 {
+            self.walk = 0.0;
+            return;
+        }
+        self.facing = if offset < 0.0 {
+            Facing::Left
+        } else {
+            Facing::Right
+        };
+        self.walk = self.facing.sign() * speed;
+    }
+}
+
+//an enemy as its behavior tree sees it for one tick
+struct EnemyAgent<'a> {
+    enemy: &'a mut Enemy,
+    player: &'a Aabb,
+    blocks: &'a [Aabb],
+    navigation: &'a NavGraph,
+    sees: bool,
+    shot: Option<Vector2<f32>>, //set when Shoot fired the weapon
+}
+
+impl Agent for EnemyAgent<'_> {
+    fn check(&mut self, condition: Condition) -> bool {
+        let enemy = &self.enemy;
+        match condition {
+            Condition::SeesPlayer => self.sees,
+            Condition::RemembersPlayer => enemy.since_seen < enemy.memory,
+            Condition::PlayerWithin(distance) => {
+                (self.player.center() - enemy.aabb().center()).magnitude() <= distance
+            }
+            Condition::HasWeapon => enemy.weapon.is_some(),
+            Condition::OnGround => enemy.is_grounded,
+            Condition::Hurt(fraction) => enemy.health.current < enemy.health.max * fraction,
+        }
+    }
+
+    fn act(&mut self, action: Action) -> Status {
+        let enemy = &mut self.enemy;
+        match action {
+            Action::Patrol => {
+                enemy.patrol(self.blocks);
+                Status::Running
+            }
+            Action::Chase => {
+                if enemy.go_to(enemy.last_seen, self.navigation, self.blocks) {
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            Action::Shoot => {
+                let direction = self.player.center() - enemy.aabb().center();
+                if direction.x != 0.0 {
+                    enemy.facing = if direction.x < 0.0 {
+                        Facing::Left
+                    } else {
+                        Facing::Right
+                    };
+                }
+                if direction.magnitude2() == 0.0 {
+                    return Status::Failure;
+                }
+                if !enemy.weapon.as_mut().is_some_and(|weapon| weapon.try_fire()) {
+                    return Status::Failure;
+                }
+                self.shot = Some(direction.normalize());
+                Status::Success
+            }
+        }
+    }
+}
+
+impl Entity for Enemy {
+    fn get_id(&self) -> usize {
+        self.id
+    }
+
+    fn get_pos(&self) -> &Point2<f32> {
+        &self.pos
+    }
+}
@@ -0,0 +1,59 @@
+//hit points of anything that can be hurt
+#[derive(Debug, Clone)]
+pub struct Health {
+    pub max: f32,
+    pub current: f32,
+    pub invulnerability: f32, //seconds nothing can hurt it after a hit
+    invulnerable_for: f32,
+    since_hit: f32, //seconds since the last hit that landed
+}
+
+impl Health {
+    pub fn new(max: f32, invulnerability: f32) -> Self {
+        Self {
+            max,
+            current: max,
+            invulnerability,
+            invulnerable_for: 0.0,
+            since_hit: f32::INFINITY,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.invulnerable_for = (self.invulnerable_for - dt).max(0.0);
+        self.since_hit += dt;
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_for > 0.0
+    }
+
+    pub fn since_hit(&self) -> f32 {
+        self.since_hit
+    }
+
+    //returns true when the hit landed, nothing happens while invulnerable or already dead
+    pub fn damage(&mut self, amount: f32) -> bool {
+        if self.is_invulnerable() || self.is_dead() || amount <= 0.0 {
+            return false;
+        }
+        self.current = (self.current - amount).max(0.0);
+        self.invulnerable_for = self.invulnerability;
+        self.since_hit = 0.0;
+        true
+    }
+
+    //dead no matter what, for falling out of the world
+    pub fn kill(&mut self) {
+        self.current = 0.0;
+    }
+
+    //back to full, for respawning
+    pub fn reset(&mut self) {
+        *self = Self::new(self.max, self.invulnerability);
+    }
+}
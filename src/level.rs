@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::ascii_map;
+use crate::data::{self, DataError, ParseError};
+use crate::tiled;
+
+//a level as it's written on disk, positions are in pixels and block lengths in tiles
+//
+//(
+//    spawn: (400.0, 400.0),
+//    blocks: [
+//        (pos: (0.0, 0.0), length: (20, 2), color: (1.0, 1.0, 1.0)),
+//    ],
+//    entities: [
+//        (kind: Enemy, pos: (600.0, 100.0), properties: {"patrol": "true"}),
+//    ],
+//)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    pub spawn: (f32, f32),
+    pub blocks: Vec<BlockDef>,
+    #[serde(default)]
+    pub entities: Vec<EntityDef>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockDef {
+    pub pos: (f32, f32),
+    pub length: (usize, usize),
+    #[serde(default = "default_block_color")]
+    pub color: (f32, f32, f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum EntityKind {
+    Enemy,
+    Pickup,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntityDef {
+    pub kind: EntityKind,
+    pub pos: (f32, f32),
+    //anything extra the level designer attached to the entity
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+impl EntityDef {
+    pub fn new(kind: EntityKind, pos: (f32, f32)) -> Self {
+        Self {
+            kind,
+            pos,
+            properties: HashMap::new(),
+        }
+    }
+}
+
+pub(crate) fn default_block_color() -> (f32, f32, f32) {
+    (1.0, 1.0, 1.0)
+}
+
+impl Level {
+    //.txt files are ascii maps, .tmj files are Tiled json exports, anything else is read as ron
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        let path = path.as_ref();
+        let source = data::read(path)?;
+
+        let level = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("txt") => ascii_map::parse(&source),
+            Some("tmj") => tiled::parse(&source),
+            _ => Self::from_ron(&source),
+        };
+        level.map_err(|err| err.in_file(path))
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, ParseError> {
+        data::from_ron(source)
+    }
+}
+
+//the level the game shipped with before levels were loaded from files
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            spawn: (400.0, 400.0),
+            blocks: vec![
+                BlockDef {
+                    pos: (200.0, 330.0),
+                    length: (6, 1),
+                    color: default_block_color(),
+                },
+                BlockDef {
+                    pos: (0.0, 0.0),
+                    length: (20, 2),
+                    color: default_block_color(),
+                },
+            ],
+            entities: Vec::new(),
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::iter;
+use std::time::{Duration, Instant};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
@@ -8,84 +10,138 @@ use winit::{
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-mod renderer;
-use renderer::Renderer;
+pub mod renderer;
+use renderer::{QuadHandle, Renderer};
 
-mod camera;
+pub mod camera;
 use camera::Camera;
 
-mod physics;
-use physics::check_player_gravity_collission;
+pub mod physics;
+use physics::lerp_pos;
 
-use cgmath::{point2, point3, Point2, Point3};
+pub mod atlas;
+use atlas::TextureAtlas;
 
-const WHITE: Point3<f32> = point3::<f32>(1.0, 1.0, 1.0);
+pub mod animation;
+use animation::{player_clips, AnimationSet, Animator};
 
-struct State {
-    surface: wgpu::Surface,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
-    size: winit::dpi::PhysicalSize<u32>,
-    window: Window,
-    renderer: Renderer,
-    camera: Camera,
-    player: Player,
-    blocks: Vec<Block>,
+pub mod data;
+pub use data::DataError;
 
-    quad_size: f32,
-}
+pub mod level;
+pub use level::Level;
 
-pub trait Entity {
-    fn get_id(&self) -> usize;
-    fn get_pos(&self) -> &Point2<f32>;
-}
+pub mod ascii_map;
+pub mod tiled;
 
-struct Player {
-    index: usize,
-    pos: Point2<f32>,
+pub mod settings;
+pub use settings::{ScalingMode, Settings};
 
-    is_left_pressed: bool,
-    is_right_pressed: bool,
-    is_space_pressed: bool,
-    is_jumping: bool,
+pub mod health;
 
-    gravity: f32,
-    speed: f32,
-    jumping_value: f32,
+pub mod behavior;
+use behavior::{Action, BehaviorTree};
 
-    timer: f64,
-    timer_limit: f64,
-}
+pub mod enemy;
 
-impl Entity for Player {
-    fn get_id(&self) -> usize {
-        self.index
-    }
+pub mod navigation;
+use navigation::{LinkKind, NavGraph};
 
-    fn get_pos(&self) -> &Point2<f32> {
-        &self.pos
-    }
-}
+pub mod weapon;
+use weapon::Team;
 
-struct Block {
-    index: usize,
-    pos: Point2<f32>,
-    length: Point2<usize>,
-}
+pub mod world;
+pub use world::{Entity, Input, World, WorldEvent};
+use world::{Facing, Movement};
+use world::{SIMULATION_HZ, TIMESTEP};
 
-impl Entity for Block {
-    fn get_id(&self) -> usize {
-        self.index
-    }
+use cgmath::{point2, point3, vec2, InnerSpace, Point2, Point3};
 
-    fn get_pos(&self) -> &Point2<f32> {
-        &self.pos
-    }
+const WHITE: Point3<f32> = point3::<f32>(1.0, 1.0, 1.0);
+//what's behind the level, the window outside the letterbox stays black
+const BACKGROUND: Point3<f32> = point3::<f32>(0.1, 0.3, 0.2);
+const BULLET_COLOR: Point3<f32> = point3::<f32>(1.0, 0.9, 0.5);
+const ROCKET_COLOR: Point3<f32> = point3::<f32>(1.0, 0.45, 0.2);
+const ENEMY_BULLET_COLOR: Point3<f32> = point3::<f32>(1.0, 0.3, 0.8);
+//tint of something that just got hurt and how long it lasts (seconds)
+const HIT_COLOR: Point3<f32> = point3::<f32>(1.0, 0.35, 0.35);
+const HIT_FLASH_TIME: f32 = 0.1;
+const TRACER_COLOR: Point3<f32> = point3::<f32>(1.0, 1.0, 0.8);
+const RETICLE_SIZE: f32 = 32.0;
+//seconds a hitscan tracer stays on screen
+const TRACER_TIME: f32 = 0.08;
+const TRACER_WIDTH: f32 = 3.0;
+//the navigation overlay (F3), platforms and the links between them by kind
+const NAV_PLATFORM_COLOR: Point3<f32> = point3::<f32>(0.3, 0.9, 1.0);
+const NAV_WALK_COLOR: Point3<f32> = point3::<f32>(0.4, 1.0, 0.4);
+const NAV_DROP_COLOR: Point3<f32> = point3::<f32>(1.0, 0.9, 0.3);
+const NAV_JUMP_COLOR: Point3<f32> = point3::<f32>(1.0, 0.4, 1.0);
+const NAV_LINE_WIDTH: f32 = 3.0;
+//straight pieces a jump link's arc is drawn with
+const NAV_ARC_SEGMENTS: usize = 8;
+//the behavior debug view (F4), a marker over each enemy colored by what it's doing
+const BEHAVIOR_MARKER_SIZE: f32 = 12.0;
+const BEHAVIOR_PATROL_COLOR: Point3<f32> = point3::<f32>(0.4, 1.0, 0.4);
+const BEHAVIOR_CHASE_COLOR: Point3<f32> = point3::<f32>(1.0, 0.6, 0.2);
+const BEHAVIOR_SHOOT_COLOR: Point3<f32> = point3::<f32>(1.0, 0.2, 0.2);
+const BEHAVIOR_WAIT_COLOR: Point3<f32> = point3::<f32>(0.4, 0.6, 1.0);
+const BEHAVIOR_IDLE_COLOR: Point3<f32> = point3::<f32>(0.6, 0.6, 0.6);
+//how far touchpad scrolling has to go to count as one wheel step
+const SCROLL_LINE_PIXELS: f32 = 50.0;
+//how much one press of the zoom keys zooms in or out
+const ZOOM_STEP: f32 = 1.25;
+//falling further than this (pixels) before landing shakes the camera, a normal jump is
+//about 160 high
+const HARD_LANDING_HEIGHT: f32 = 250.0;
+
+//every png in here ends up in the texture atlas, named after its file
+const SPRITES_DIR: &str = "assets/sprites";
+//what the player carries, in the order the number keys pick them
+const WEAPONS_FILE: &str = "assets/weapons.ron";
+const ENEMIES_FILE: &str = "assets/enemies.ron";
+
+//caps how much time one frame can feed the simulation (after a hitch or dragging the window)
+//so it doesn't spiral trying to catch up
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    size: winit::dpi::PhysicalSize<u32>,
+    window: Window,
+    renderer: Renderer,
+    camera: Camera,
+    atlas: TextureAtlas,
+
+    world: World,
+    background_quad: QuadHandle,
+    player_quad: QuadHandle,
+    player_clips: AnimationSet,
+    player_animator: Animator,
+    //quads for entities that come and go, keyed by entity id
+    entity_quads: HashMap<usize, QuadHandle>,
+    //hitscan tracers and how many seconds they have left
+    tracers: Vec<(QuadHandle, f32)>,
+    reticle_quad: QuadHandle,
+    //the navigation overlay, empty while it's hidden
+    navigation_quads: Vec<QuadHandle>,
+    //the behavior debug view, markers keyed by enemy id and the node each was running
+    show_behaviors: bool,
+    behavior_markers: HashMap<usize, (QuadHandle, Option<usize>)>,
+
+    //logical pixels from the top left of the window, None while it's outside
+    cursor: Option<Point2<f32>>,
+    //the fire key and the mouse button both fire, letting go of one keeps the other going
+    fire_key: bool,
+    fire_button: bool,
+    //touchpad scrolling that hasn't added up to a whole line yet
+    scroll_pixels: f32,
 }
 
 impl State {
-    async fn new(window: Window) -> Self {
+    async fn new(window: Window, world: World, settings: Settings) -> Self {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -136,8 +192,7 @@ impl State {
             .formats
             .iter()
             .copied()
-            .filter(|f| f.describe().srgb)
-            .next()
+            .find(|f| f.describe().srgb)
             .unwrap_or(surface_caps.formats[0]);
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -155,62 +210,61 @@ impl State {
             source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
         });
 
-        let camera = Camera::new(&config, &device);
-        let quad_size = 50.0;
+        let mut camera = Camera::new(&config, &device, world.player.aabb().center(), &settings);
+        camera.bounds = Some(world.bounds());
+        camera.snap_to(world.player.aabb().center());
+
+        //without sprites everything is still drawn, just as flat colored quads
+        let atlas = TextureAtlas::load_dir(SPRITES_DIR).unwrap_or_else(|err| {
+            log::warn!("{}", err);
+            TextureAtlas::pack(Vec::new())
+        });
 
         let mut renderer = Renderer::new(
             &device,
+            &queue,
             &config,
             &shader,
             camera.bind_group_layout(),
-            quad_size,
+            &atlas,
         );
 
-        let player_pos = point2::<f32>(config.width as f32 / 2.0, config.height as f32 / 2.0 + 100.0);
-        let player_index = renderer.create_quad(player_pos, point3::<f32>(0.0, 1.0, 0.0));
-        let player =  Player {
-                speed: 5.0,
-                is_left_pressed: false,
-                is_right_pressed: false,
-                is_space_pressed: false,
-                is_jumping: false,
-                gravity: 8.0,
-                jumping_value: 14.0,
-                index: player_index,
-                pos: player_pos,
-                timer: 0.0,
-                timer_limit: 0.8,
-            };
+        //created first so it's drawn under everything, it covers whatever the camera sees
+        let background_quad = renderer.create_quad(
+            camera.view().center,
+            camera.view().visible_size(),
+            BACKGROUND,
+        );
 
-        let center_block_length = point2::<usize>(6, 1);
-        let center_block_pos = point2::<f32>(200.0, 330.0);
-        let center_block_index = renderer.create_block(
-            center_block_pos,
-            center_block_length,
+        //the renderer only mirrors the world, quads are created once for everything in it
+        //and the player quad gets moved around every frame
+        let player_quad = renderer.create_sprite(
+            world.player.pos,
+            world.player.aabb().size,
+            atlas.region_or_white("player_idle_0"),
             WHITE,
         );
-        let center_block = Block {
-                index: center_block_index,
-                pos: center_block_pos,
-                length: center_block_length,
-         };
-
-        let ground_block_length = point2::<usize>(20, 2);
-        let ground_block_pos = point2::<f32>(0.0, 0.0);
-        let ground_block_index = renderer.create_block(
-            ground_block_pos,
-            ground_block_length,
+        for block in world.blocks.iter() {
+            //the block sprite is one tile, repeated over the whole block and tinted by its color
+            let block_quad = renderer.create_sprite(
+                block.pos,
+                block.aabb().size,
+                atlas.region_or_white("block"),
+                block.color,
+            );
+            let tiling = vec2::<f32>(block.length.x as f32, block.length.y as f32);
+            if let Err(err) = renderer.set_quad_tiling(block_quad, tiling) {
+                log::warn!("{}", err);
+            }
+        }
+
+        //starts out invisible until the cursor is over the window
+        let reticle_quad = renderer.create_sprite(
+            world.spawn,
+            vec2::<f32>(0.0, 0.0),
+            atlas.region_or_white("reticle"),
             WHITE,
         );
-        let ground_block = Block{
-            index: ground_block_index,
-            pos: ground_block_pos,
-            length: ground_block_length,
-        };
-
-        let mut blocks = Vec::new();
-        blocks.push(center_block);
-        blocks.push(ground_block);
 
         Self {
             surface,
@@ -221,9 +275,22 @@ impl State {
             window,
             renderer,
             camera,
-            player,
-            blocks,
-            quad_size,
+            atlas,
+            world,
+            background_quad,
+            player_quad,
+            player_clips: player_clips(),
+            player_animator: Animator::new("idle"),
+            entity_quads: HashMap::new(),
+            tracers: Vec::new(),
+            reticle_quad,
+            navigation_quads: Vec::new(),
+            show_behaviors: false,
+            behavior_markers: HashMap::new(),
+            cursor: None,
+            fire_key: false,
+            fire_button: false,
+            scroll_pixels: 0.0,
         }
     }
 
@@ -237,6 +304,7 @@ impl State {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.camera.resize(new_size.width, new_size.height);
         }
     }
 
@@ -254,84 +322,382 @@ impl State {
                 let is_pressed = *state == ElementState::Pressed;
                 match keycode {
                     VirtualKeyCode::A => {
-                        self.player.is_left_pressed = is_pressed;
+                        self.world.input.left = is_pressed;
                         true
                     }
                     VirtualKeyCode::D => {
-                        self.player.is_right_pressed = is_pressed;
+                        self.world.input.right = is_pressed;
                         true
                     }
                     VirtualKeyCode::Space => {
-                        self.player.is_space_pressed = is_pressed;
+                        self.world.input.set_jump(is_pressed);
+                        true
+                    }
+                    VirtualKeyCode::F => {
+                        self.fire_key = is_pressed;
+                        self.world.input.fire = self.fire_key || self.fire_button;
+                        true
+                    }
+                    VirtualKeyCode::R => {
+                        if is_pressed {
+                            self.world.input.reload = true;
+                        }
+                        true
+                    }
+                    VirtualKeyCode::Key1
+                    | VirtualKeyCode::Key2
+                    | VirtualKeyCode::Key3
+                    | VirtualKeyCode::Key4
+                    | VirtualKeyCode::Key5 => {
+                        if is_pressed {
+                            let slot = match keycode {
+                                VirtualKeyCode::Key1 => 0,
+                                VirtualKeyCode::Key2 => 1,
+                                VirtualKeyCode::Key3 => 2,
+                                VirtualKeyCode::Key4 => 3,
+                                _ => 4,
+                            };
+                            self.world.input.select_weapon = Some(slot);
+                        }
+                        true
+                    }
+                    VirtualKeyCode::F3 => {
+                        if is_pressed {
+                            self.toggle_navigation_overlay();
+                        }
+                        true
+                    }
+                    VirtualKeyCode::F4 => {
+                        if is_pressed {
+                            self.toggle_behavior_view();
+                        }
+                        true
+                    }
+                    VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd => {
+                        if is_pressed {
+                            self.camera.zoom_by(ZOOM_STEP);
+                        }
+                        true
+                    }
+                    VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => {
+                        if is_pressed {
+                            self.camera.zoom_by(1.0 / ZOOM_STEP);
+                        }
                         true
                     }
                     _ => false,
                 }
             }
+            WindowEvent::CursorMoved { position, .. } => {
+                let logical = position.to_logical::<f32>(self.window.scale_factor());
+                self.cursor = Some(point2::<f32>(logical.x, logical.y));
+                true
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                true
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.fire_button = *state == ElementState::Pressed;
+                self.world.input.fire = self.fire_key || self.fire_button;
+                true
+            }
+            //scrolling down goes to the next weapon, touchpads scroll by pixels a bit at a
+            //time so those add up to a line before anything switches
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        self.scroll_pixels += position.y as f32;
+                        let lines = (self.scroll_pixels / SCROLL_LINE_PIXELS).trunc();
+                        self.scroll_pixels -= lines * SCROLL_LINE_PIXELS;
+                        lines
+                    }
+                };
+                if lines != 0.0 {
+                    self.world.input.cycle_weapon -= lines.signum() as i32;
+                }
+                true
+            }
             _ => false,
         }
     }
 
-    fn update(&mut self) {
-        //horizontal movement
-        if self.player.is_right_pressed {
-            self.renderer.update_quad_data(self.player.index, point2::<f32>(self.player.speed, 0.0));
-            self.player.pos = point2::<f32>(self.player.pos.x + self.player.speed, self.player.pos.y);
+    //draws the enemies' navigation graph on top of everything or takes it away again
+    fn toggle_navigation_overlay(&mut self) {
+        if !self.navigation_quads.is_empty() {
+            for quad in self.navigation_quads.drain(..) {
+                if let Err(err) = self.renderer.remove_quad(quad) {
+                    log::warn!("{}", err);
+                }
+            }
+            return;
         }
-        if self.player.is_left_pressed {
-            self.renderer.update_quad_data(self.player.index, point2::<f32>(-self.player.speed, 0.0));
-            self.player.pos = point2::<f32>(self.player.pos.x - self.player.speed, self.player.pos.y);
+        self.navigation_quads = navigation_overlay(&mut self.renderer, &self.world.navigation);
+    }
+
+    fn toggle_behavior_view(&mut self) {
+        self.show_behaviors = !self.show_behaviors;
+        for (_, (quad, _)) in self.behavior_markers.drain() {
+            if let Err(err) = self.renderer.remove_quad(quad) {
+                log::warn!("{}", err);
+            }
         }
+    }
 
-        //vertical movement
-        let player_pos_after_gravity = point2::<f32>(self.player.pos.x, self.player.pos.y - self.player.gravity);
-        //pre-calculating where the player will be after adding gravity to shorthand function arguments
-        let mut player_pos_on_block = point2::<f32>(0.0, 0.0);
-        //player position on top of the block he's colliding with 
-
-        let mut is_colliding = false; //is the player colliding with ANY block
-        //collission detection
-        for block in self.blocks.iter(){
-            let collission_info = check_player_gravity_collission(
-            player_pos_after_gravity,
-            block.pos,
-            self.quad_size,
-            block.length,
-            );
+    //moves the markers over the enemies and logs whenever one switches to another node
+    fn sync_behavior_markers(&mut self, alpha: f32) {
+        if !self.show_behaviors {
+            return;
+        }
+        for enemy in self.world.enemies.iter() {
+            let current = enemy.brain.current();
+            let size = vec2::<f32>(BEHAVIOR_MARKER_SIZE, BEHAVIOR_MARKER_SIZE);
+            let aabb = enemy.aabb();
+            let pos = lerp_pos(enemy.prev_pos, enemy.pos, alpha)
+                + vec2::<f32>((aabb.size.x - size.x) / 2.0, aabb.size.y + size.y / 2.0);
+            let color = behavior_color(enemy.tree(), current);
+
+            let renderer = &mut self.renderer;
+            let (quad, shown) = self
+                .behavior_markers
+                .entry(enemy.get_id())
+                .or_insert_with(|| (renderer.create_quad(pos, size, color), None));
+            if *shown != current {
+                let node = current.map(|index| enemy.tree().describe(index));
+                log::info!("enemy {}: {}", enemy.get_id(), node.as_deref().unwrap_or("nothing"));
+                *shown = current;
+            }
+            if let Err(err) = renderer
+                .set_quad_position(*quad, pos)
+                .and_then(|_| renderer.set_quad_color(*quad, color))
+            {
+                log::warn!("{}", err);
+            }
+        }
+
+        let world = &self.world;
+        let renderer = &mut self.renderer;
+        self.behavior_markers.retain(|id, (quad, _)| {
+            let alive = world.enemies.iter().any(|enemy| enemy.get_id() == *id);
+            if !alive {
+                if let Err(err) = renderer.remove_quad(*quad) {
+                    log::warn!("{}", err);
+                }
+            }
+            alive
+        });
+    }
+
+    //where the cursor points in the world
+    fn cursor_world(&self) -> Option<Point2<f32>> {
+        let scale_factor = self.window.scale_factor() as f32;
+        self.cursor.map(|cursor| self.camera.screen_to_world(cursor, scale_factor))
+    }
 
-            if let Some(player_pos) = collission_info{
-                is_colliding = true;
-                player_pos_on_block = player_pos;
+    fn update(&mut self) {
+        //the camera moves every frame so the aim is worked out again even if the mouse didn't move
+        self.world.input.aim = self
+            .cursor_world()
+            .map(|cursor| cursor - self.world.player.aabb().center());
+        self.world.tick(TIMESTEP);
+
+        let mut shot = false;
+        for event in self.world.events() {
+            match *event {
+                WorldEvent::Shot { direction } => {
+                    shot = true;
+                    let recoil = self.world.player.weapon().def.recoil;
+                    if recoil > 0.0 {
+                        self.camera.punch(-direction, recoil * 0.03, 0.1);
+                    }
+                }
+                WorldEvent::Hitscan { from, to } => {
+                    let renderer = &mut self.renderer;
+                    let quad = create_line(renderer, from, to, TRACER_WIDTH, TRACER_COLOR);
+                    self.tracers.push((quad, TRACER_TIME));
+                }
+                WorldEvent::Explosion { radius, .. } => {
+                    self.camera.add_trauma((radius / 200.0).min(0.8));
+                }
+                WorldEvent::Damaged { entity, amount } if entity == self.world.player.get_id() => {
+                    self.camera.add_trauma((amount / 50.0).min(0.6));
+                }
+                //the player is already back at the spawn, the camera jumps there with them
+                WorldEvent::Died { entity, .. } if entity == self.world.player.get_id() => {
+                    self.camera.snap_to(self.world.player.aabb().center());
+                }
+                WorldEvent::Landed { speed, height } if height >= HARD_LANDING_HEIGHT => {
+                    let hardness = speed / self.world.player.max_fall_speed;
+                    self.camera.add_trauma(0.4 * hardness);
+                    self.camera.punch(vec2::<f32>(0.0, -1.0), 12.0 * hardness, 0.2);
+                }
+                _ => {}
             }
+        }
 
+        let clip = match self.world.player.movement() {
+            Movement::Idle => "idle",
+            Movement::Running => "run",
+            Movement::Jumping => "jump",
+            Movement::Falling => "fall",
+        };
+        if shot {
+            self.player_animator.restart("shoot");
+        } else if self.player_animator.clip() != "shoot" || self.player_animator.is_finished() {
+            self.player_animator.play(clip);
         }
-        if is_colliding{
-            self.renderer.change_quad_data(self.player.index, player_pos_on_block);
-            self.player.pos = player_pos_on_block;
-        }else{
-            self.renderer.update_quad_data(self.player.index, point2::<f32>(0.0, -self.player.gravity));
-            self.player.pos = player_pos_after_gravity;
+        self.player_animator.advance(&self.player_clips, TIMESTEP);
+    }
+
+    //bullets and enemies come and go, quads are made for new ones and dropped for
+    //the ones that are gone
+    fn sync_entity_quads(&mut self, alpha: f32) {
+        let bullet_sprite = self.atlas.region_or_white("bullet");
+        for bullet in self.world.bullets.iter() {
+            let pos = lerp_pos(bullet.prev_pos, bullet.pos, alpha);
+            match self.entity_quads.get(&bullet.get_id()) {
+                Some(&quad) => {
+                    if let Err(err) = self.renderer.set_quad_position(quad, pos) {
+                        log::warn!("{}", err);
+                    }
+                }
+                None => {
+                    let color = if bullet.splash_radius > 0.0 {
+                        ROCKET_COLOR
+                    } else if bullet.team == Team::Enemy {
+                        ENEMY_BULLET_COLOR
+                    } else {
+                        BULLET_COLOR
+                    };
+                    let quad = self.renderer.create_sprite(pos, bullet.size, bullet_sprite, color);
+                    self.entity_quads.insert(bullet.get_id(), quad);
+                }
+            }
         }
 
-        //JUMPING
-        /*
-        if self.player.is_space_pressed  || self.player.is_jumping{
-            self.player.is_jumping = true;
-            self.player.timer = self.player.timer + 0.1;
-            if self.player.timer >= self.player.timer_limit{
-                self.player.is_jumping = false;
-                self.player.timer = 0.0;
+        let enemy_sprite = self.atlas.region_or_white("enemy");
+        for enemy in self.world.enemies.iter() {
+            let pos = lerp_pos(enemy.prev_pos, enemy.pos, alpha);
+            let quad = *self.entity_quads.entry(enemy.get_id()).or_insert_with(|| {
+                self.renderer.create_sprite(pos, enemy.aabb().size, enemy_sprite, WHITE)
+            });
+            let color = if enemy.health.since_hit() < HIT_FLASH_TIME {
+                HIT_COLOR
+            } else {
+                WHITE
+            };
+            //the enemy art faces right like the player's
+            let sprite = if enemy.facing == Facing::Left {
+                enemy_sprite.flipped_x()
+            } else {
+                enemy_sprite
+            };
+            if let Err(err) = self
+                .renderer
+                .set_quad_position(quad, pos)
+                .and_then(|_| self.renderer.set_quad_color(quad, color))
+                .and_then(|_| self.renderer.set_quad_sprite(quad, sprite))
+            {
+                log::warn!("{}", err);
             }
-                self.renderer
-                  .update_quad_data(self.player.index, point2::<f32>(0.0, self.player.jumping_value));
-            self.player.pos = point2::<f32>(self.player.pos.x, self.player.pos.y + self.player.jumping_value);
-            self.player.is_space_pressed = false;
         }
-        */
-        
+
+        let world = &self.world;
+        let renderer = &mut self.renderer;
+        self.entity_quads.retain(|id, quad| {
+            let alive = world.bullets.iter().any(|bullet| bullet.get_id() == *id)
+                || world.enemies.iter().any(|enemy| enemy.get_id() == *id);
+            if !alive {
+                if let Err(err) = renderer.remove_quad(*quad) {
+                    log::warn!("{}", err);
+                }
+            }
+            alive
+        });
     }
 
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    //alpha is how far (0..1) we are between the last two simulation steps,
+    //frame_time is how many seconds passed since the last frame
+    fn render(&mut self, alpha: f32, frame_time: f32) -> Result<(), wgpu::SurfaceError> {
+        let player_pos = lerp_pos(self.world.player.prev_pos, self.world.player.pos, alpha);
+        if let Err(err) = self.renderer.set_quad_position(self.player_quad, player_pos) {
+            log::warn!("{}", err);
+        }
+
+        //following the interpolated position keeps the player from jittering on screen
+        let player_center = player_pos + self.world.player.aabb().size / 2.0;
+        self.camera.follow(player_center, frame_time);
+        self.camera.update_effects(frame_time);
+        self.camera.update_buffer(&self.queue);
+
+        //a shaking camera is turned and pushed around, a square as wide as the screen's diagonal
+        //still covers all of it
+        let view = self.camera.view();
+        let diagonal = view.visible_size().magnitude();
+        let background = vec2::<f32>(diagonal, diagonal);
+        if let Err(err) = self
+            .renderer
+            .set_quad_position(self.background_quad, view.center - background / 2.0)
+            .and_then(|_| self.renderer.set_quad_size(self.background_quad, background))
+        {
+            log::warn!("{}", err);
+        }
+
+        //the player art faces right, mirror it when walking left
+        let frame = self.player_animator.sprite(&self.player_clips).unwrap_or("player_idle_0");
+        let mut sprite = self.atlas.region_or_white(frame);
+        if self.world.player.facing == Facing::Left {
+            sprite = sprite.flipped_x();
+        }
+        if let Err(err) = self.renderer.set_quad_sprite(self.player_quad, sprite) {
+            log::warn!("{}", err);
+        }
+
+        //blinks while nothing can hurt the player after a hit
+        let health = &self.world.player.health;
+        let flashes = (health.since_hit() / HIT_FLASH_TIME) as u32;
+        let blink = health.is_invulnerable() && flashes.is_multiple_of(2);
+        let tint = if blink { HIT_COLOR } else { WHITE };
+        if let Err(err) = self.renderer.set_quad_color(self.player_quad, tint) {
+            log::warn!("{}", err);
+        }
+
+        self.sync_entity_quads(alpha);
+        self.sync_behavior_markers(alpha);
+
+        //the reticle shrinks away while the cursor is outside the window
+        let (reticle_pos, reticle_size) = match self.cursor_world() {
+            Some(cursor) => {
+                let size = vec2::<f32>(RETICLE_SIZE, RETICLE_SIZE) / self.camera.zoom();
+                (cursor - size / 2.0, size)
+            }
+            None => (player_center, vec2::<f32>(0.0, 0.0)),
+        };
+        if let Err(err) = self
+            .renderer
+            .set_quad_position(self.reticle_quad, reticle_pos)
+            .and_then(|_| self.renderer.set_quad_size(self.reticle_quad, reticle_size))
+        {
+            log::warn!("{}", err);
+        }
+
+        let renderer = &mut self.renderer;
+        self.tracers.retain_mut(|(quad, left)| {
+            *left -= frame_time;
+            if *left > 0.0 {
+                return true;
+            }
+            if let Err(err) = renderer.remove_quad(*quad) {
+                log::warn!("{}", err);
+            }
+            false
+        });
+
         let output = self.surface.get_current_texture()?;
         let view = output
             .texture
@@ -342,7 +708,10 @@ impl State {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
-        let buffers = self.renderer.collect_buffers(&self.device);
+        if let Err(err) = self.renderer.upload_buffers(&self.device, &self.queue) {
+            log::error!("{}", err);
+        }
+        let buffers = self.renderer.buffers();
 
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -351,23 +720,29 @@ impl State {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            b: 0.2,
-                            g: 0.3,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
 
+            let viewport = self.camera.viewport();
+            render_pass.set_viewport(
+                viewport.x,
+                viewport.y,
+                viewport.width,
+                viewport.height,
+                0.0,
+                1.0,
+            );
             render_pass.set_pipeline(&self.renderer.render_pipeline);
             render_pass.set_bind_group(0, self.camera.bind_group(), &[]);
+            render_pass.set_bind_group(1, self.renderer.texture_bind_group(), &[]);
             render_pass.set_vertex_buffer(0, buffers.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(buffers.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..buffers.num_of_indices, 0, 0..1);
+            render_pass.set_vertex_buffer(1, buffers.instance_buffer.slice(..));
+            render_pass.set_index_buffer(buffers.index_buffer.slice(..), buffers.index_format);
+            render_pass.draw_indexed(0..buffers.num_of_indices, 0, 0..buffers.num_of_instances);
         }
         self.queue.submit(iter::once(encoder.finish()));
         output.present(); //draws the stuff to the surface texture
@@ -375,14 +750,120 @@ impl State {
     }
 }
 
+//marker color for the node an enemy is running, a leaf that isn't an action is a wait
+fn behavior_color(tree: &BehaviorTree, current: Option<usize>) -> Point3<f32> {
+    match current.map(|index| tree.action(index)) {
+        Some(Some(Action::Patrol)) => BEHAVIOR_PATROL_COLOR,
+        Some(Some(Action::Chase)) => BEHAVIOR_CHASE_COLOR,
+        Some(Some(Action::Shoot)) => BEHAVIOR_SHOOT_COLOR,
+        Some(None) => BEHAVIOR_WAIT_COLOR,
+        None => BEHAVIOR_IDLE_COLOR,
+    }
+}
+
+//a thin quad laid from one point to the other and turned to match
+fn create_line(
+    renderer: &mut Renderer,
+    from: Point2<f32>,
+    to: Point2<f32>,
+    width: f32,
+    color: Point3<f32>,
+) -> QuadHandle {
+    let line = to - from;
+    let size = vec2::<f32>(line.magnitude(), width);
+    let middle = from + line / 2.0;
+    let quad = renderer.create_quad(middle - size / 2.0, size, color);
+    if let Err(err) = renderer.set_quad_rotation(quad, line.y.atan2(line.x)) {
+        log::warn!("{}", err);
+    }
+    quad
+}
+
+//platforms as lines along their tops and links from where they start to where they land,
+//jumps follow the arc they were worked out from
+fn navigation_overlay(renderer: &mut Renderer, navigation: &NavGraph) -> Vec<QuadHandle> {
+    let mut quads = Vec::new();
+    for platform in navigation.platforms.iter() {
+        let left = point2::<f32>(platform.left, platform.y);
+        let right = point2::<f32>(platform.right, platform.y);
+        quads.push(create_line(renderer, left, right, NAV_LINE_WIDTH, NAV_PLATFORM_COLOR));
+    }
+
+    let arc = navigation.arc;
+    for link in navigation.links.iter().flatten() {
+        let from = point2::<f32>(link.from_x, navigation.platforms[link.from].y);
+        let to = point2::<f32>(link.to_x, navigation.platforms[link.to].y);
+        match link.kind {
+            //the platforms touch so there's no line, a dot where they meet
+            LinkKind::Walk => {
+                let size = vec2::<f32>(NAV_LINE_WIDTH, NAV_LINE_WIDTH) * 3.0;
+                quads.push(renderer.create_quad(from - size / 2.0, size, NAV_WALK_COLOR));
+            }
+            LinkKind::Drop => {
+                quads.push(create_line(renderer, from, to, NAV_LINE_WIDTH, NAV_DROP_COLOR));
+            }
+            LinkKind::Jump => {
+                let airtime = arc.reach(to.y - from.y).unwrap_or(0.0) / arc.speed;
+                let across = to.x - from.x;
+                let point = |t: f32| {
+                    let x = from.x + across.signum() * (arc.speed * t).min(across.abs());
+                    let y = from.y + arc.jump_impulse * t - arc.gravity * t * t / 2.0;
+                    point2::<f32>(x, y)
+                };
+                for i in 0..NAV_ARC_SEGMENTS {
+                    let start = point(airtime * i as f32 / NAV_ARC_SEGMENTS as f32);
+                    let end = point(airtime * (i + 1) as f32 / NAV_ARC_SEGMENTS as f32);
+                    quads.push(create_line(renderer, start, end, NAV_LINE_WIDTH, NAV_JUMP_COLOR));
+                }
+            }
+        }
+    }
+    quads
+}
+
+//builds the world for a level with the enemies and weapons from the data files, the
+//built in ones stand in for files that can't be loaded
+pub fn load_world(level: &Level) -> World {
+    let enemy_types = enemy::load_types(ENEMIES_FILE).unwrap_or_else(|err| {
+        log::warn!("{}, using the built in enemies", err);
+        enemy::default_types()
+    });
+    let mut world = World::from_level_with(level, &enemy_types);
+    match weapon::load_weapons(WEAPONS_FILE) {
+        Ok(weapons) => world.player.set_weapons(weapons),
+        Err(err) => log::warn!("{}, the player only gets a pistol", err),
+    }
+    world
+}
+
+//warnings (like data files that didn't load) show up without having to set RUST_LOG
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init_logger() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+}
+
+//runs the simulation for the given number of ticks without opening a window,
+//so the game logic can be exercised on machines without a gpu
+pub fn run_headless(level: &Level, ticks: u32) -> World {
+    let mut world = load_world(level);
+    for _ in 0..ticks {
+        world.tick(TIMESTEP);
+    }
+    world
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub async fn run() {
+    run_level(Level::default(), Settings::default()).await
+}
+
+pub async fn run_level(level: Level, settings: Settings) {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             std::panic::set_hook(Box::new(console_error_panic_hook::hook));
             console_log::init_with_level(log::Level::Warn).expect("Could't initialize logger");
         } else {
-            env_logger::init();
+            init_logger();
         }
     }
 
@@ -408,41 +889,55 @@ pub async fn run() {
             .expect("Couldn't append canvas to document body.");
     }
 
+    let world = load_world(&level);
+
     // State::new uses async code, so we're going to wait for it to finish
-    let mut state = State::new(window).await;
+    let mut state = State::new(window, world, settings).await;
+
+    let fixed_timestep = Duration::from_secs(1) / SIMULATION_HZ;
+    let mut accumulator = Duration::ZERO;
+    let mut last_frame = Instant::now();
 
     event_loop.run(move |event, _, control_flow| {
         match event {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == state.window().id() => {
-                if !state.input(event) {
-                    match event {
-                        WindowEvent::CloseRequested
-                        | WindowEvent::KeyboardInput {
-                            input:
-                                KeyboardInput {
-                                    state: ElementState::Pressed,
-                                    virtual_keycode: Some(VirtualKeyCode::Escape),
-                                    ..
-                                },
-                            ..
-                        } => *control_flow = ControlFlow::Exit,
-                        WindowEvent::Resized(physical_size) => {
-                            state.resize(*physical_size);
-                        }
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            // new_inner_size is &mut so w have to dereference it twice
-                            state.resize(**new_inner_size);
-                        }
-                        _ => {}
+            } if window_id == state.window().id() && !state.input(event) => {
+                match event {
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        // new_inner_size is &mut so w have to dereference it twice
+                        state.resize(**new_inner_size);
                     }
+                    _ => {}
                 }
             }
             Event::RedrawRequested(window_id) if window_id == state.window().id() => {
-                state.update();
-                match state.render() {
+                let now = Instant::now();
+                let frame_time = (now - last_frame).min(MAX_FRAME_TIME);
+                accumulator += frame_time;
+                last_frame = now;
+
+                while accumulator >= fixed_timestep {
+                    state.update();
+                    accumulator -= fixed_timestep;
+                }
+
+                let alpha = accumulator.as_secs_f32() / fixed_timestep.as_secs_f32();
+                match state.render(alpha, frame_time.as_secs_f32()) {
                     Ok(_) => {}
                     // Reconfigure the surface if it's lost or outdated
                     Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
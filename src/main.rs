@@ -1,5 +1,61 @@
-use shooter_game::run;
+use shooter_game::{init_logger, run_headless, run_level, Level, Settings};
 
 fn main() {
-    pollster::block_on(run());
+    let args: Vec<String> = std::env::args().collect();
+
+    //shooter_game [level.ron] [--headless [ticks]] [--scaling window|letterbox|integer]
+    //             [--no-screen-shake]
+    let mut level_path = None;
+    let mut headless = None;
+    let mut settings = Settings::default();
+    let mut args_iter = args.iter().skip(1).peekable();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            //--headless [ticks] runs the simulation without a window and prints how it ended up
+            "--headless" => {
+                let ticks = args_iter.next_if(|ticks| ticks.parse::<u32>().is_ok());
+                headless = Some(ticks.and_then(|ticks| ticks.parse().ok()).unwrap_or(600));
+            }
+            "--scaling" => match args_iter.next().map(|mode| mode.parse()) {
+                Some(Ok(mode)) => settings.scaling = mode,
+                Some(Err(err)) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("--scaling needs a mode: window, letterbox or integer");
+                    std::process::exit(1);
+                }
+            },
+            "--no-screen-shake" => settings.screen_shake = false,
+            _ if !arg.starts_with("--") && level_path.is_none() => level_path = Some(arg),
+            _ => eprintln!("ignoring unknown argument {}", arg),
+        }
+    }
+
+    let level = match level_path {
+        Some(path) => match Level::load(path) {
+            Ok(level) => level,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        },
+        None => Level::default(),
+    };
+
+    if let Some(ticks) = headless {
+        //run_level sets up logging itself
+        init_logger();
+        let world = run_headless(&level, ticks);
+        println!(
+            "after {} ticks the player is at {:?} with {} enemies left",
+            ticks,
+            world.player.pos,
+            world.enemies.len()
+        );
+        return;
+    }
+
+    pollster::block_on(run_level(level, settings));
 }
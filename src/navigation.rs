@@ -0,0 +1,462 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use cgmath::{point2, vec2, InnerSpace, Point2, Vector2};
+
+use crate::physics::Aabb;
+
+//how close two edges have to be (pixels) to count as touching
+const EPSILON: f32 = 0.5;
+
+//the top of a block something can stand on, the parts covered by other blocks are cut out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Platform {
+    pub left: f32,
+    pub right: f32,
+    pub y: f32,
+}
+
+impl Platform {
+    pub fn center(&self) -> Point2<f32> {
+        point2::<f32>((self.left + self.right) / 2.0, self.y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    //the next platform starts where this one ends
+    Walk,
+    //walk off the edge and fall onto it
+    Drop,
+    //needs a jump from from_x
+    Jump,
+}
+
+//a way from one platform to another, from_x is where to be on the first one to take
+//it and to_x where it lands on the other
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Link {
+    pub from: usize,
+    pub to: usize,
+    pub kind: LinkKind,
+    pub from_x: f32,
+    pub to_x: f32,
+    pub cost: f32,
+}
+
+//how whatever walks the graph moves, the links are made from this
+#[derive(Debug, Clone, Copy)]
+pub struct JumpArc {
+    pub speed: f32,        //pixels per second sideways
+    pub gravity: f32,      //pixels per second squared
+    pub jump_impulse: f32, //pixels per second up when jumping
+}
+
+impl JumpArc {
+    pub fn max_height(&self) -> f32 {
+        self.jump_impulse * self.jump_impulse / (2.0 * self.gravity)
+    }
+
+    //how far sideways a jump gets before it comes down to rise above where it started
+    //(below for a negative rise), None when it can't get that high
+    pub fn reach(&self, rise: f32) -> Option<f32> {
+        let v = self.jump_impulse;
+        let root = v * v - 2.0 * self.gravity * rise;
+        if root < 0.0 {
+            return None;
+        }
+        let time = (v + root.sqrt()) / self.gravity;
+        Some(self.speed * time)
+    }
+}
+
+//platforms and the links between them, built once from a level's blocks
+pub struct NavGraph {
+    pub platforms: Vec<Platform>,
+    pub links: Vec<Vec<Link>>, //links going out of each platform
+    pub arc: JumpArc,          //what the jump links assume, following them has to match it
+    agent: Vector2<f32>,
+}
+
+impl NavGraph {
+    //agent is the size of what walks the graph
+    pub fn build(blocks: &[Aabb], agent: Vector2<f32>, arc: JumpArc) -> Self {
+        let platforms = find_platforms(blocks, agent);
+        let mut graph = Self {
+            links: vec![Vec::new(); platforms.len()],
+            platforms,
+            arc,
+            agent,
+        };
+
+        for from in 0..graph.platforms.len() {
+            graph.link_walks(from);
+            graph.link_drops(from, blocks);
+        }
+        for from in 0..graph.platforms.len() {
+            for to in 0..graph.platforms.len() {
+                let linked = graph.links[from].iter().any(|link| link.to == to);
+                if from != to && !linked {
+                    graph.link_jump(from, to, blocks);
+                }
+            }
+        }
+        graph
+    }
+
+    fn add_link(&mut self, from: usize, to: usize, kind: LinkKind, from_x: f32, to_x: f32) {
+        let start = point2::<f32>(from_x, self.platforms[from].y);
+        let end = point2::<f32>(to_x, self.platforms[to].y);
+        //never cheaper than the straight line between the platforms so that line works
+        //as the A* estimate
+        let penalty = match kind {
+            LinkKind::Walk => 1.0,
+            LinkKind::Drop => 1.2,
+            LinkKind::Jump => 1.5,
+        };
+        let centers = (self.platforms[to].center() - self.platforms[from].center()).magnitude();
+        let travelled = (start - self.platforms[from].center()).magnitude()
+            + (end - start).magnitude()
+            + (self.platforms[to].center() - end).magnitude();
+        self.links[from].push(Link {
+            from,
+            to,
+            kind,
+            from_x,
+            to_x,
+            cost: travelled.max(centers) * penalty,
+        });
+    }
+
+    //platforms at the same height that touch are walked between
+    fn link_walks(&mut self, from: usize) {
+        let platform = self.platforms[from];
+        for to in 0..self.platforms.len() {
+            let other = self.platforms[to];
+            if to == from || (other.y - platform.y).abs() > EPSILON {
+                continue;
+            }
+            if (other.left - platform.right).abs() <= EPSILON {
+                self.add_link(from, to, LinkKind::Walk, platform.right, other.left);
+            } else if (platform.left - other.right).abs() <= EPSILON {
+                self.add_link(from, to, LinkKind::Walk, platform.left, other.right);
+            }
+        }
+    }
+
+    //walking off either edge falls onto whatever is right below it
+    fn link_drops(&mut self, from: usize, blocks: &[Aabb]) {
+        let platform = self.platforms[from];
+        let half = self.agent.x / 2.0;
+        for (edge, x) in [
+            (platform.left, platform.left - half),
+            (platform.right, platform.right + half),
+        ] {
+            let walked_on = self.links[from]
+                .iter()
+                .any(|link| link.kind == LinkKind::Walk && link.from_x == edge);
+            //a wall right next to the edge
+            let step = Aabb::new(point2::<f32>(x - half, platform.y), self.agent);
+            if walked_on || blocks.iter().any(|block| block.overlaps(&step)) {
+                continue;
+            }
+
+            let landing = (0..self.platforms.len())
+                .filter(|&to| {
+                    let other = self.platforms[to];
+                    other.y < platform.y - EPSILON
+                        && other.left < x + half
+                        && other.right > x - half
+                })
+                .max_by(|&a, &b| self.platforms[a].y.total_cmp(&self.platforms[b].y));
+            let to = match landing {
+                Some(to) => to,
+                None => continue,
+            };
+            let other = self.platforms[to];
+            let column = Aabb::new(
+                point2::<f32>(x - half, other.y),
+                vec2::<f32>(self.agent.x, platform.y - other.y),
+            );
+            if blocks.iter().any(|block| block.overlaps(&column)) {
+                continue;
+            }
+            self.add_link(from, to, LinkKind::Drop, edge, x.clamp(other.left, other.right));
+        }
+    }
+
+    //jumps towards the near edge of the other platform, the takeoff has to be clear of
+    //it from below and the arc has to cover the distance. the arc itself is only checked
+    //against a box at its highest point so low ceilings can still fool it
+    fn link_jump(&mut self, from: usize, to: usize, blocks: &[Aabb]) {
+        let platform = self.platforms[from];
+        let other = self.platforms[to];
+        let half = self.agent.x / 2.0;
+        let rise = other.y - platform.y;
+        let reach = match self.arc.reach(rise) {
+            Some(reach) => reach,
+            None => return,
+        };
+
+        let (takeoff, landing) = if other.left >= platform.center().x {
+            ((other.left - half).min(platform.right), other.left + half)
+        } else {
+            ((other.right + half).max(platform.left), other.right - half)
+        };
+        if takeoff < platform.left - EPSILON || takeoff > platform.right + EPSILON {
+            return;
+        }
+        if (landing - takeoff).abs() > reach {
+            return;
+        }
+
+        let top = platform.y.max(other.y);
+        let column = Aabb::new(
+            point2::<f32>(takeoff - half, platform.y),
+            vec2::<f32>(self.agent.x, top - platform.y + self.agent.y),
+        );
+        let band = Aabb::new(
+            point2::<f32>(takeoff.min(landing) - half, top),
+            vec2::<f32>((landing - takeoff).abs() + self.agent.x, self.agent.y),
+        );
+        if blocks
+            .iter()
+            .any(|block| block.overlaps(&column) || block.overlaps(&band))
+        {
+            return;
+        }
+        self.add_link(from, to, LinkKind::Jump, takeoff, landing);
+    }
+
+    //the platform something with its feet (bottom middle) at feet is standing on
+    pub fn platform_at(&self, feet: Point2<f32>) -> Option<usize> {
+        let half = self.agent.x / 2.0;
+        self.nearest(|platform| {
+            (platform.y - feet.y).abs() <= 2.0
+                && feet.x >= platform.left - half
+                && feet.x <= platform.right + half
+        }, feet)
+    }
+
+    //the closest platform under feet, for things that are in the air
+    pub fn platform_below(&self, feet: Point2<f32>) -> Option<usize> {
+        let half = self.agent.x / 2.0;
+        self.nearest(|platform| {
+            platform.y <= feet.y + 2.0
+                && feet.x >= platform.left - half
+                && feet.x <= platform.right + half
+        }, feet)
+    }
+
+    fn nearest(&self, matches: impl Fn(&Platform) -> bool, feet: Point2<f32>) -> Option<usize> {
+        let distance = |platform: &Platform| {
+            let x = feet.x.clamp(platform.left, platform.right);
+            (point2::<f32>(x, platform.y) - feet).magnitude()
+        };
+        (0..self.platforms.len())
+            .filter(|&i| matches(&self.platforms[i]))
+            .min_by(|&a, &b| distance(&self.platforms[a]).total_cmp(&distance(&self.platforms[b])))
+    }
+
+    //A* from one platform to another, the links to take in order. empty when they're the
+    //same platform and None when there's no way there
+    pub fn find_path(&self, from: usize, to: usize) -> Option<Vec<Link>> {
+        let estimate = |node: usize| {
+            (self.platforms[to].center() - self.platforms[node].center()).magnitude()
+        };
+
+        let mut cost = vec![f32::INFINITY; self.platforms.len()];
+        let mut came_by: Vec<Option<Link>> = vec![None; self.platforms.len()];
+        let mut open = BinaryHeap::new();
+        cost[from] = 0.0;
+        open.push(Candidate {
+            node: from,
+            priority: estimate(from),
+        });
+
+        while let Some(Candidate { node, priority }) = open.pop() {
+            if node == to {
+                let mut path = Vec::new();
+                let mut current = to;
+                while let Some(link) = came_by[current] {
+                    path.push(link);
+                    current = link.from;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            //stale entry, the node was reached cheaper since it was pushed
+            if priority > cost[node] + estimate(node) {
+                continue;
+            }
+            for link in self.links[node].iter() {
+                let new_cost = cost[node] + link.cost;
+                if new_cost < cost[link.to] {
+                    cost[link.to] = new_cost;
+                    came_by[link.to] = Some(*link);
+                    open.push(Candidate {
+                        node: link.to,
+                        priority: new_cost + estimate(link.to),
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+//open set entry, the heap pops the lowest priority first
+struct Candidate {
+    node: usize,
+    priority: f32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+    }
+}
+
+//tops of blocks minus whatever is in the way of standing there
+fn find_platforms(blocks: &[Aabb], agent: Vector2<f32>) -> Vec<Platform> {
+    let mut platforms = Vec::new();
+    for (i, block) in blocks.iter().enumerate() {
+        let y = block.max().y;
+        let mut segments = vec![(block.pos.x, block.max().x)];
+
+        let standing = Aabb::new(point2::<f32>(block.pos.x, y), vec2::<f32>(block.size.x, agent.y));
+        for (j, other) in blocks.iter().enumerate() {
+            if i == j || !other.overlaps(&standing) {
+                continue;
+            }
+            let (cut_left, cut_right) = (other.pos.x, other.max().x);
+            segments = segments
+                .into_iter()
+                .flat_map(|(left, right)| {
+                    let mut kept = Vec::new();
+                    if cut_left > left {
+                        kept.push((left, cut_left.min(right)));
+                    }
+                    if cut_right < right {
+                        kept.push((cut_right.max(left), right));
+                    }
+                    kept
+                })
+                .filter(|(left, right)| right - left > EPSILON)
+                .collect();
+        }
+
+        platforms.extend(
+            segments
+                .into_iter()
+                .map(|(left, right)| Platform { left, right, y }),
+        );
+    }
+    platforms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENT: Vector2<f32> = vec2::<f32>(50.0, 50.0);
+    const ARC: JumpArc = JumpArc {
+        speed: 300.0,
+        gravity: 2160.0,
+        jump_impulse: 840.0,
+    };
+
+    fn block(x: f32, y: f32, width: f32, height: f32) -> Aabb {
+        Aabb::new(point2::<f32>(x, y), vec2::<f32>(width, height))
+    }
+
+    //index of the platform with its left end at left and its top at y
+    fn platform(graph: &NavGraph, left: f32, y: f32) -> usize {
+        graph
+            .platforms
+            .iter()
+            .position(|platform| platform.left == left && platform.y == y)
+            .unwrap_or_else(|| panic!("no platform at {} {} in {:?}", left, y, graph.platforms))
+    }
+
+    #[test]
+    fn jumps_across_a_gap_it_can_reach() {
+        let blocks = [block(0.0, 50.0, 200.0, 50.0), block(300.0, 50.0, 200.0, 50.0)];
+        let graph = NavGraph::build(&blocks, AGENT, ARC);
+        let (left, right) = (platform(&graph, 0.0, 100.0), platform(&graph, 300.0, 100.0));
+
+        let path = graph.find_path(left, right).unwrap();
+        assert_eq!(path.len(), 1);
+        assert_eq!(path[0].kind, LinkKind::Jump);
+        //takes off from the edge and lands fully on the other side
+        assert_eq!((path[0].from_x, path[0].to_x), (200.0, 325.0));
+        assert!(graph.find_path(right, left).is_some());
+        assert_eq!(graph.find_path(left, left), Some(Vec::new()));
+    }
+
+    #[test]
+    fn gaps_wider_than_a_jump_arent_linked() {
+        let blocks = [block(0.0, 50.0, 200.0, 50.0), block(600.0, 50.0, 200.0, 50.0)];
+        let graph = NavGraph::build(&blocks, AGENT, ARC);
+        let (left, right) = (platform(&graph, 0.0, 100.0), platform(&graph, 600.0, 100.0));
+
+        assert!(graph.find_path(left, right).is_none());
+    }
+
+    #[test]
+    fn drops_off_a_ledge_too_high_to_jump_back_onto() {
+        let blocks = [block(0.0, 0.0, 1000.0, 100.0), block(0.0, 250.0, 200.0, 50.0)];
+        let graph = NavGraph::build(&blocks, AGENT, ARC);
+        let (ground, ledge) = (platform(&graph, 0.0, 100.0), platform(&graph, 0.0, 300.0));
+
+        let path = graph.find_path(ledge, ground).unwrap();
+        assert_eq!(path.len(), 1);
+        assert_eq!(path[0].kind, LinkKind::Drop);
+        assert_eq!((path[0].from_x, path[0].to_x), (200.0, 225.0));
+        //200 up is more than a jump gets
+        assert!(graph.find_path(ground, ledge).is_none());
+    }
+
+    #[test]
+    fn a_wall_at_the_takeoff_blocks_the_jump() {
+        let blocks = [
+            block(0.0, 50.0, 200.0, 50.0),
+            block(300.0, 50.0, 200.0, 50.0),
+            //standing on the right end of the first block
+            block(150.0, 100.0, 50.0, 200.0),
+        ];
+        let graph = NavGraph::build(&blocks, AGENT, ARC);
+
+        //the wall cuts the first block's top short
+        let left = platform(&graph, 0.0, 100.0);
+        assert_eq!(graph.platforms[left].right, 150.0);
+        let right = platform(&graph, 300.0, 100.0);
+        assert!(graph.find_path(left, right).is_none());
+    }
+
+    #[test]
+    fn finds_what_is_stood_on() {
+        let blocks = [block(0.0, 0.0, 1000.0, 100.0), block(0.0, 250.0, 200.0, 50.0)];
+        let graph = NavGraph::build(&blocks, AGENT, ARC);
+        let (ground, ledge) = (platform(&graph, 0.0, 100.0), platform(&graph, 0.0, 300.0));
+
+        assert_eq!(graph.platform_at(point2::<f32>(100.0, 300.0)), Some(ledge));
+        assert_eq!(graph.platform_at(point2::<f32>(100.0, 200.0)), None);
+        //in the air under the ledge the ground is what's below
+        assert_eq!(graph.platform_below(point2::<f32>(100.0, 200.0)), Some(ground));
+    }
+}
@@ -1,20 +1,299 @@
-use cgmath::{point2, Point2};
-
-//works to check gravity
-pub fn check_player_gravity_collission(
-    player_pos: Point2<f32>,
-    block_pos: Point2<f32>,
-    quad_size: f32,
-    block_length: Point2<usize>,
-) -> Option<Point2<f32>> {
-    //println!("Player pos {:?}, block pos {:?}", player_pos, block_pos);
-
-    if player_pos.x + quad_size >= block_pos.x 
-    && player_pos.x < block_pos.x + (quad_size * block_length.x as f32)
-    && player_pos.y + quad_size > block_pos.y
-    && player_pos.y <= block_pos.y + (quad_size * block_length.y as f32){
-       //println!("colliding");
-        return Some(point2::<f32>(player_pos.x, (block_pos.y + (quad_size * block_length.y as f32))));
-    }
-    None
+use cgmath::{point2, vec2, Point2, Vector2};
+
+//axis aligned box, pos is the bottom left corner (same as quads in the renderer)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub pos: Point2<f32>,
+    pub size: Vector2<f32>,
+}
+
+impl Aabb {
+    pub fn new(pos: Point2<f32>, size: Vector2<f32>) -> Self {
+        Self { pos, size }
+    }
+
+    pub fn max(&self) -> Point2<f32> {
+        point2::<f32>(self.pos.x + self.size.x, self.pos.y + self.size.y)
+    }
+
+    pub fn center(&self) -> Point2<f32> {
+        self.pos + self.size / 2.0
+    }
+
+    //smallest box containing both
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let max = self.max();
+        let other_max = other.max();
+        let pos = point2::<f32>(self.pos.x.min(other.pos.x), self.pos.y.min(other.pos.y));
+        Aabb::new(
+            pos,
+            vec2::<f32>(max.x.max(other_max.x) - pos.x, max.y.max(other_max.y) - pos.y),
+        )
+    }
+
+    //touching edges don't count as overlapping, otherwise standing on a block
+    //would also register as hitting the side of the block next to it
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        let max = self.max();
+        let other_max = other.max();
+        self.pos.x < other_max.x
+            && max.x > other.pos.x
+            && self.pos.y < other_max.y
+            && max.y > other.pos.y
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+//normal points away from the block, towards the side the mover got pushed out of
+//(so (0, 1) means the mover is standing on top of the block)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub normal: Vector2<f32>,
+    pub penetration: f32,
+}
+
+pub struct MoveResult {
+    pub pos: Point2<f32>,
+    pub contacts: Vec<Contact>,
+}
+
+impl MoveResult {
+    pub fn touched(&self, normal: Vector2<f32>) -> bool {
+        self.contacts.iter().any(|contact| contact.normal == normal)
+    }
+}
+
+//checks the mover against a block along a single axis, delta is how far the mover
+//travelled along that axis this step and decides which side of the block was hit
+pub fn resolve_axis(mover: &Aabb, block: &Aabb, axis: Axis, delta: f32) -> Option<Contact> {
+    if !mover.overlaps(block) {
+        return None;
+    }
+
+    let (mover_min, mover_max, block_min, block_max) = match axis {
+        Axis::X => (mover.pos.x, mover.max().x, block.pos.x, block.max().x),
+        Axis::Y => (mover.pos.y, mover.max().y, block.pos.y, block.max().y),
+    };
+
+    //without movement along the axis fall back to comparing centers
+    let moving_positive = if delta != 0.0 {
+        delta > 0.0
+    } else {
+        mover_min + mover_max < block_min + block_max
+    };
+
+    let (sign, penetration) = if moving_positive {
+        (-1.0, mover_max - block_min)
+    } else {
+        (1.0, block_max - mover_min)
+    };
+
+    let normal = match axis {
+        Axis::X => vec2::<f32>(sign, 0.0),
+        Axis::Y => vec2::<f32>(0.0, sign),
+    };
+
+    Some(Contact {
+        normal,
+        penetration,
+    })
+}
+
+//moves the box horizontally first and then vertically, pushing it out of every
+//block it ends up inside of after each step
+pub fn move_and_collide(
+    mover: Aabb,
+    delta: Vector2<f32>,
+    blocks: &[Aabb],
+) -> MoveResult {
+    let mut moved = mover;
+    let mut contacts = Vec::new();
+
+    for (axis, axis_delta) in [(Axis::X, delta.x), (Axis::Y, delta.y)] {
+        match axis {
+            Axis::X => moved.pos.x += axis_delta,
+            Axis::Y => moved.pos.y += axis_delta,
+        }
+
+        for block in blocks.iter() {
+            if let Some(contact) = resolve_axis(&moved, block, axis, axis_delta) {
+                moved.pos += contact.normal * contact.penetration;
+                contacts.push(contact);
+            }
+        }
+    }
+
+    MoveResult {
+        pos: moved.pos,
+        contacts,
+    }
+}
+
+//where something that moved from prev to pos during the last tick is drawn, alpha is how
+//far (0..1) we are between the last two ticks
+pub fn lerp_pos(prev: Point2<f32>, pos: Point2<f32>, alpha: f32) -> Point2<f32> {
+    prev + (pos - prev) * alpha
+}
+
+//slab test, returns how far along the ray (in units of direction's length) it first enters
+//the box, a ray starting inside the box hits it at 0
+pub fn ray_cast(origin: Point2<f32>, direction: Vector2<f32>, target: &Aabb) -> Option<f32> {
+    let max = target.max();
+    let mut t_min = 0.0_f32;
+    let mut t_max = f32::INFINITY;
+
+    for (origin, direction, min, max) in [
+        (origin.x, direction.x, target.pos.x, max.x),
+        (origin.y, direction.y, target.pos.y, max.y),
+    ] {
+        if direction == 0.0 {
+            //parallel to this pair of sides, it has to start between them
+            if origin < min || origin > max {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min - origin) / direction;
+        let t2 = (max - origin) / direction;
+        t_min = t_min.max(t1.min(t2));
+        t_max = t_max.min(t1.max(t2));
+        if t_min > t_max {
+            return None;
+        }
+    }
+
+    Some(t_min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVER: Vector2<f32> = vec2::<f32>(50.0, 50.0);
+
+    //100x100 block from (100, 100) to (200, 200)
+    fn block() -> Aabb {
+        Aabb::new(point2::<f32>(100.0, 100.0), vec2::<f32>(100.0, 100.0))
+    }
+
+    fn mover(x: f32, y: f32) -> Aabb {
+        Aabb::new(point2::<f32>(x, y), MOVER)
+    }
+
+    //moves by delta into the block and checks it got pushed out to pos through one contact
+    fn bump(from: Aabb, delta: Vector2<f32>, pos: Point2<f32>, normal: Vector2<f32>) {
+        let result = move_and_collide(from, delta, &[block()]);
+        assert_eq!(result.pos, pos);
+        assert_eq!(result.contacts.len(), 1, "{:?}", result.contacts);
+        assert_eq!(result.contacts[0].normal, normal);
+        assert!(result.touched(normal));
+    }
+
+    #[test]
+    fn walking_right_hits_the_left_wall() {
+        bump(
+            mover(40.0, 120.0),
+            vec2::<f32>(20.0, 0.0),
+            point2::<f32>(50.0, 120.0),
+            vec2::<f32>(-1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn walking_left_hits_the_right_wall() {
+        bump(
+            mover(210.0, 120.0),
+            vec2::<f32>(-20.0, 0.0),
+            point2::<f32>(200.0, 120.0),
+            vec2::<f32>(1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn falling_lands_on_top() {
+        bump(
+            mover(120.0, 210.0),
+            vec2::<f32>(0.0, -20.0),
+            point2::<f32>(120.0, 200.0),
+            vec2::<f32>(0.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn jumping_bumps_the_head_on_the_bottom() {
+        bump(
+            mover(120.0, 40.0),
+            vec2::<f32>(0.0, 20.0),
+            point2::<f32>(120.0, 50.0),
+            vec2::<f32>(0.0, -1.0),
+        );
+    }
+
+    #[test]
+    fn moving_along_the_top_doesnt_touch_anything() {
+        let result = move_and_collide(mover(120.0, 200.0), vec2::<f32>(30.0, 0.0), &[block()]);
+        assert_eq!(result.pos, point2::<f32>(150.0, 200.0));
+        assert!(result.contacts.is_empty());
+    }
+
+    #[test]
+    fn penetration_is_how_far_the_mover_is_inside() {
+        let contact = resolve_axis(&mover(65.0, 120.0), &block(), Axis::X, 15.0).unwrap();
+        assert_eq!(contact.normal, vec2::<f32>(-1.0, 0.0));
+        assert_eq!(contact.penetration, 15.0);
+
+        let contact = resolve_axis(&mover(120.0, 170.0), &block(), Axis::Y, -40.0).unwrap();
+        assert_eq!(contact.normal, vec2::<f32>(0.0, 1.0));
+        assert_eq!(contact.penetration, 30.0);
+
+        assert_eq!(resolve_axis(&mover(40.0, 120.0), &block(), Axis::X, 10.0), None);
+    }
+
+    #[test]
+    fn without_movement_the_closer_side_wins() {
+        //the mover's center is left of the block's so it's pushed out to the left
+        let contact = resolve_axis(&mover(60.0, 120.0), &block(), Axis::X, 0.0).unwrap();
+        assert_eq!(contact.normal, vec2::<f32>(-1.0, 0.0));
+        assert_eq!(contact.penetration, 10.0);
+
+        let contact = resolve_axis(&mover(190.0, 120.0), &block(), Axis::X, 0.0).unwrap();
+        assert_eq!(contact.normal, vec2::<f32>(1.0, 0.0));
+        assert_eq!(contact.penetration, 10.0);
+
+        let contact = resolve_axis(&mover(120.0, 180.0), &block(), Axis::Y, 0.0).unwrap();
+        assert_eq!(contact.normal, vec2::<f32>(0.0, 1.0));
+        assert_eq!(contact.penetration, 20.0);
+    }
+
+    #[test]
+    fn ray_cast_hits_in_units_of_direction() {
+        let origin = point2::<f32>(0.0, 150.0);
+        assert_eq!(ray_cast(origin, vec2::<f32>(1.0, 0.0), &block()), Some(100.0));
+        assert_eq!(ray_cast(origin, vec2::<f32>(2.0, 0.0), &block()), Some(50.0));
+        //diagonally into the bottom
+        assert_eq!(
+            ray_cast(point2::<f32>(50.0, 50.0), vec2::<f32>(1.0, 1.0), &block()),
+            Some(50.0)
+        );
+        //starting inside
+        assert_eq!(
+            ray_cast(point2::<f32>(150.0, 150.0), vec2::<f32>(1.0, 0.0), &block()),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn ray_cast_misses() {
+        //passes over the top
+        assert_eq!(ray_cast(point2::<f32>(0.0, 250.0), vec2::<f32>(1.0, 0.0), &block()), None);
+        //points away
+        assert_eq!(ray_cast(point2::<f32>(0.0, 150.0), vec2::<f32>(-1.0, 0.0), &block()), None);
+        //goes by below the corner
+        assert_eq!(ray_cast(point2::<f32>(0.0, 0.0), vec2::<f32>(1.0, 0.25), &block()), None);
+    }
 }
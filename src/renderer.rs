@@ -1,64 +1,172 @@
-use cgmath::{point2, Point2, Point3};
+use cgmath::{vec2, Point2, Point3, Vector2};
+use std::fmt;
+use std::ops::Range;
 use wgpu::util::DeviceExt;
 
+use crate::atlas::{TextureAtlas, UvRect};
+
+//how many quads the instance buffer can hold before it first has to grow
+const INITIAL_QUAD_CAPACITY: usize = 64;
+
+//every quad is this unit square scaled and moved into place by its instance data
+const QUAD_VERTICES: [Vertex; 4] = [
+    Vertex { position: [0.0, 0.0] },
+    Vertex { position: [1.0, 0.0] },
+    Vertex { position: [0.0, 1.0] },
+    Vertex { position: [1.0, 1.0] },
+];
+//32 bit indices so the index type never limits how much can be drawn,
+//INDEX_FORMAT has to match the type of QUAD_INDICES
+const QUAD_INDICES: [u32; 6] = [0, 1, 2, 2, 1, 3];
+const INDEX_FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32;
+
+#[derive(Debug)]
+pub enum RendererError {
+    //more quads than the instance buffer is allowed to hold on this device
+    TooManyQuads { count: usize, max: usize },
+    //the quad was removed, possibly with its slot reused by a newer quad since
+    InvalidHandle(QuadHandle),
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RendererError::TooManyQuads { count, max } => write!(
+                f,
+                "{} quads don't fit in the instance buffer, the device allows at most {}",
+                count, max
+            ),
+            RendererError::InvalidHandle(handle) => write!(
+                f,
+                "quad handle {}v{} doesn't point to a live quad",
+                handle.slot, handle.generation
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
+
+//refers to a quad for as long as it exists, once the quad is removed the generation of its
+//slot goes up so the old handle gets rejected instead of touching whatever reused the slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QuadHandle {
+    slot: u32,
+    generation: u32,
+}
+
+struct Slot {
+    generation: u32,
+    index: Option<usize>, //where the quad currently sits in quads/instances, None when free
+}
+
+//pos is the bottom left corner, size is in pixels and color tints the sprite
 struct QuadInfo {
     pos: Point2<f32>,
+    size: Vector2<f32>,
     color: Point3<f32>,
+    sprite: UvRect,
+    tiling: Vector2<f32>,
+    rotation: f32,
 }
 
 impl QuadInfo {
-    fn new(pos: Point2<f32>, color: Point3<f32>) -> Self {
-        Self { pos, color }
+    fn instance(&self) -> Instance {
+        Instance {
+            position: self.pos.into(),
+            size: self.size.into(),
+            color: self.color.into(),
+            uv_rect: [
+                self.sprite.min[0],
+                self.sprite.min[1],
+                self.sprite.max[0],
+                self.sprite.max[1],
+            ],
+            tiling: self.tiling.into(),
+            rotation: self.rotation,
+        }
     }
 }
 
 pub struct Renderer {
     pub render_pipeline: wgpu::RenderPipeline,
 
+    texture_bind_group: wgpu::BindGroup,
+    white: UvRect,
+
+    //quads, instances and owners are packed, removing a quad moves the last one into its place
     quads: Vec<QuadInfo>,
-    quad_size: f32,
-    current_quad_index: usize,
-    vertices: Vec<Vertex>,
-    indices: Vec<u16>,
+    instances: Vec<Instance>,
+    owners: Vec<usize>, //slot of every packed quad
+    slots: Vec<Slot>,
+    free_slots: Vec<usize>,
+
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    //the gpu copy of the instances lives as long as the renderer, only the instances that
+    //changed since the last frame get written and it's only recreated when it's full
+    instance_buffer: wgpu::Buffer,
+    quad_capacity: usize,
+    dirty_quads: Option<Range<usize>>, //quads whose instances changed since the last upload
 }
 
-pub struct Buffers {
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
+pub struct Buffers<'a> {
+    pub vertex_buffer: &'a wgpu::Buffer,
+    pub index_buffer: &'a wgpu::Buffer,
+    pub instance_buffer: &'a wgpu::Buffer,
+    pub index_format: wgpu::IndexFormat,
     pub num_of_indices: u32,
+    pub num_of_instances: u32,
 }
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct Vertex {
     position: [f32; 2],
-    color: [f32; 3],
 }
 
 impl Vertex {
-    fn new(position: Point2<f32>, color: Point3<f32>) -> Self {
-        Self {
-            position: position.into(),
-            color: color.into(),
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: wgpu::VertexFormat::Float32x2,
+            }],
         }
     }
+}
+
+//per quad data, position is the bottom left corner and the sprite in uv_rect is
+//repeated tiling times across the quad
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Instance {
+    position: [f32; 2],
+    size: [f32; 2],
+    color: [f32; 3],
+    uv_rect: [f32; 4],
+    tiling: [f32; 2],
+    rotation: f32,
+}
+
+impl Instance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        1 => Float32x2,
+        2 => Float32x2,
+        3 => Float32x3,
+        4 => Float32x4,
+        5 => Float32x2,
+        6 => Float32,
+    ];
 
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
         }
     }
 }
@@ -66,16 +174,20 @@ impl Vertex {
 impl Renderer {
     pub fn new(
         device: &wgpu::Device,
+        queue: &wgpu::Queue,
         config: &wgpu::SurfaceConfiguration,
         shader: &wgpu::ShaderModule,
         camera_bind_group: &wgpu::BindGroupLayout,
-        size: f32,
+        atlas: &TextureAtlas,
     ) -> Self {
+        let (texture_bind_group_layout, texture_bind_group) =
+            Self::create_atlas_texture(device, queue, atlas);
+
         //describes available binding group of the pipeline
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render pipeline layout"),
-                bind_group_layouts: &[camera_bind_group],
+                bind_group_layouts: &[camera_bind_group, &texture_bind_group_layout],
                 push_constant_ranges: &[],
             });
         //describes shaders, buffers and stuff
@@ -83,19 +195,16 @@ impl Renderer {
             label: Some("Render pipeline"),
             layout: Some(&render_pipeline_layout),
             vertex: wgpu::VertexState {
-                module: &shader,
+                module: shader,
                 entry_point: "vs_main",
-                buffers: &[Vertex::desc()], //this is not the contents of vertex buffers but how vertex data is laid out (VertexBufferLayout)
+                buffers: &[Vertex::desc(), Instance::desc()], //this is not the contents of vertex buffers but how vertex data is laid out (VertexBufferLayout)
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
+                module: shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
                     format: config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
@@ -117,137 +226,339 @@ impl Renderer {
             multiview: None,
         });
 
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex buffer"),
+            contents: bytemuck::cast_slice(&QUAD_VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("index buffer"),
+            contents: bytemuck::cast_slice(&QUAD_INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
         Self {
             render_pipeline,
+            texture_bind_group,
+            white: atlas.white(),
             quads: Vec::new(),
-            quad_size: size,
-            vertices: Vec::new(),
-            indices: Vec::new(),
-            current_quad_index: 0,
+            instances: Vec::new(),
+            owners: Vec::new(),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            vertex_buffer,
+            index_buffer,
+            instance_buffer: Self::create_instance_buffer(device, INITIAL_QUAD_CAPACITY),
+            quad_capacity: INITIAL_QUAD_CAPACITY,
+            dirty_quads: None,
         }
     }
 
-    pub fn create_quad(&mut self, position: Point2<f32>, color: Point3<f32>) -> usize {
-        self.quads.push(QuadInfo::new(position, color));
-        let index = self.current_quad_index;
-        self.current_quad_index = self.current_quad_index + 1;
-
-        self.vertices.push(Vertex::new(position, color));
-        self.vertices.push(Vertex::new(
-            point2::<f32>(position.x + self.quad_size, position.y),
-            color,
-        ));
-        self.vertices.push(Vertex::new(
-            point2::<f32>(position.x, position.y + self.quad_size),
-            color,
-        ));
-        self.vertices.push(Vertex::new(
-            point2::<f32>(position.x + self.quad_size, position.y + self.quad_size),
-            color,
-        ));
-
-        self.indices.push((index * 4).try_into().unwrap());
-        self.indices.push((index * 4 + 1).try_into().unwrap());
-        self.indices.push((index * 4 + 2).try_into().unwrap());
-        self.indices.push((index * 4 + 2).try_into().unwrap());
-        self.indices.push((index * 4 + 1).try_into().unwrap());
-        self.indices.push((index * 4 + 3).try_into().unwrap());
-
-        index
-    }
-
-    pub fn create_block(
+    fn create_atlas_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        atlas: &TextureAtlas,
+    ) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+        let size = wgpu::Extent3d {
+            width: atlas.image.width(),
+            height: atlas.image.height(),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Atlas texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &atlas.image,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * size.width),
+                rows_per_image: std::num::NonZeroU32::new(size.height),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        //nearest keeps the pixel art crisp
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Atlas sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("texture bind group layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("texture bind group"),
+        });
+
+        (layout, bind_group)
+    }
+
+    pub fn texture_bind_group(&self) -> &wgpu::BindGroup {
+        &self.texture_bind_group
+    }
+
+    fn create_instance_buffer(device: &wgpu::Device, quad_capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance buffer"),
+            size: (quad_capacity * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn mark_dirty(&mut self, index: usize) {
+        self.dirty_quads = Some(match self.dirty_quads.take() {
+            Some(range) => range.start.min(index)..range.end.max(index + 1),
+            None => index..index + 1,
+        });
+    }
+
+    fn index_of(&self, handle: QuadHandle) -> Result<usize, RendererError> {
+        self.slots
+            .get(handle.slot as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.index)
+            .ok_or(RendererError::InvalidHandle(handle))
+    }
+
+    //flat colored quad
+    pub fn create_quad(
         &mut self,
         position: Point2<f32>,
-        length: Point2<usize>,
+        size: Vector2<f32>,
         color: Point3<f32>,
-    ) -> usize {
-        self.quads.push(QuadInfo::new(position, color));
-        let index = self.current_quad_index;
-        self.current_quad_index = self.current_quad_index + 1;
-
-        self.vertices.push(Vertex::new(position, color));
-        self.vertices.push(Vertex::new(
-            point2::<f32>(position.x + (self.quad_size * length.x as f32), position.y),
-            color,
-        ));
-        self.vertices.push(Vertex::new(
-            point2::<f32>(position.x, position.y + (self.quad_size * length.y as f32)),
-            color,
-        ));
-        self.vertices.push(Vertex::new(
-            point2::<f32>(
-                position.x + (self.quad_size * length.x as f32),
-                position.y + (self.quad_size * length.y as f32),
-            ),
-            color,
-        ));
-
-        self.indices.push((index * 4).try_into().unwrap());
-        self.indices.push((index * 4 + 1).try_into().unwrap());
-        self.indices.push((index * 4 + 2).try_into().unwrap());
-        self.indices.push((index * 4 + 2).try_into().unwrap());
-        self.indices.push((index * 4 + 1).try_into().unwrap());
-        self.indices.push((index * 4 + 3).try_into().unwrap());
-
-        index
-    }
-
-    pub fn update_quad_data(&mut self, index: usize, delta_position: Point2<f32>) {
-        let prev_pos = self.quads[index].pos;
-        let new_quad_pos =
-            point2::<f32>(prev_pos.x + delta_position.x, prev_pos.y + delta_position.y);
-        let color = self.quads[index].color;
-
-
-        self.vertices[4 * index] = Vertex::new(new_quad_pos, color);
-        self.vertices[4 * index + 1] =
-            Vertex::new(point2::<f32>(new_quad_pos.x + self.quad_size, new_quad_pos.y), color);
-        self.vertices[4 * index + 2] =
-            Vertex::new(point2::<f32>(new_quad_pos.x, new_quad_pos.y + self.quad_size), color);
-        self.vertices[4 * index + 3] = Vertex::new(
-            point2::<f32>(new_quad_pos.x + self.quad_size, new_quad_pos.y + self.quad_size),
-            color,
-        );
+    ) -> QuadHandle {
+        self.create_sprite(position, size, self.white, color)
+    }
+
+    //textured quad, the sprite's colors get multiplied by tint
+    pub fn create_sprite(
+        &mut self,
+        position: Point2<f32>,
+        size: Vector2<f32>,
+        sprite: UvRect,
+        tint: Point3<f32>,
+    ) -> QuadHandle {
+        let index = self.quads.len();
+        let slot = match self.free_slots.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    index: None,
+                });
+                self.slots.len() - 1
+            }
+        };
+        self.slots[slot].index = Some(index);
 
-        self.quads[index].pos = new_quad_pos;
+        let quad = QuadInfo {
+            pos: position,
+            size,
+            color: tint,
+            sprite,
+            tiling: vec2::<f32>(1.0, 1.0),
+            rotation: 0.0,
+        };
+        self.instances.push(quad.instance());
+        self.quads.push(quad);
+        self.owners.push(slot);
+        self.mark_dirty(index);
+
+        QuadHandle {
+            slot: slot as u32,
+            generation: self.slots[slot].generation,
+        }
     }
 
-    pub fn change_quad_data(&mut self, index: usize, new_position: Point2<f32>) {
-        let color = self.quads[index].color;
+    pub fn remove_quad(&mut self, handle: QuadHandle) -> Result<(), RendererError> {
+        let index = self.index_of(handle)?;
 
-        self.vertices[4 * index] = Vertex::new(new_position, color);
-        self.vertices[4 * index + 1] =
-            Vertex::new(point2::<f32>(new_position.x + self.quad_size, new_position.y), color);
-        self.vertices[4 * index + 2] =
-            Vertex::new(point2::<f32>(new_position.x, new_position.y + self.quad_size), color);
-        self.vertices[4 * index + 3] = Vertex::new(
-            point2::<f32>(new_position.x + self.quad_size, new_position.y + self.quad_size),
-            color,
-        );
+        self.quads.swap_remove(index);
+        self.instances.swap_remove(index);
+        self.owners.swap_remove(index);
+
+        //the last quad took the removed one's place
+        if index < self.quads.len() {
+            self.slots[self.owners[index]].index = Some(index);
+            self.mark_dirty(index);
+        }
 
-        self.quads[index].pos = new_position;
+        let slot = &mut self.slots[handle.slot as usize];
+        slot.index = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.slot as usize);
+
+        Ok(())
     }
 
-    pub fn collect_buffers(&mut self, device: &wgpu::Device) -> Buffers {
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex buffer"),
-            contents: bytemuck::cast_slice(&self.vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+    //applies a change to a quad and queues its instance for upload
+    fn edit_quad(
+        &mut self,
+        handle: QuadHandle,
+        edit: impl FnOnce(&mut QuadInfo),
+    ) -> Result<(), RendererError> {
+        let index = self.index_of(handle)?;
+        edit(&mut self.quads[index]);
+        self.instances[index] = self.quads[index].instance();
+        self.mark_dirty(index);
+        Ok(())
+    }
 
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("index buffer"),
-            contents: bytemuck::cast_slice(&self.indices),
-            usage: wgpu::BufferUsages::INDEX,
-        });
+    pub fn move_quad(
+        &mut self,
+        handle: QuadHandle,
+        delta_position: Vector2<f32>,
+    ) -> Result<(), RendererError> {
+        self.edit_quad(handle, |quad| quad.pos += delta_position)
+    }
+
+    pub fn set_quad_position(
+        &mut self,
+        handle: QuadHandle,
+        position: Point2<f32>,
+    ) -> Result<(), RendererError> {
+        self.edit_quad(handle, |quad| quad.pos = position)
+    }
+
+    pub fn set_quad_size(
+        &mut self,
+        handle: QuadHandle,
+        size: Vector2<f32>,
+    ) -> Result<(), RendererError> {
+        self.edit_quad(handle, |quad| quad.size = size)
+    }
+
+    pub fn set_quad_color(
+        &mut self,
+        handle: QuadHandle,
+        color: Point3<f32>,
+    ) -> Result<(), RendererError> {
+        self.edit_quad(handle, |quad| quad.color = color)
+    }
+
+    pub fn set_quad_sprite(
+        &mut self,
+        handle: QuadHandle,
+        sprite: UvRect,
+    ) -> Result<(), RendererError> {
+        self.edit_quad(handle, |quad| quad.sprite = sprite)
+    }
+
+    //how many times the sprite repeats across the quad on each axis
+    pub fn set_quad_tiling(
+        &mut self,
+        handle: QuadHandle,
+        tiling: Vector2<f32>,
+    ) -> Result<(), RendererError> {
+        self.edit_quad(handle, |quad| quad.tiling = tiling)
+    }
 
-        let num_of_indices = (6 * self.current_quad_index).try_into().unwrap();
+    //radians counter clockwise around the middle of the quad
+    pub fn set_quad_rotation(
+        &mut self,
+        handle: QuadHandle,
+        rotation: f32,
+    ) -> Result<(), RendererError> {
+        self.edit_quad(handle, |quad| quad.rotation = rotation)
+    }
+
+    //sends every instance that changed since the last call to the gpu, has to be called before buffers().
+    //if the quads outgrow what the device can hold the ones that fit are still uploaded and drawn
+    pub fn upload_buffers(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), RendererError> {
+        let max_quads = (device.limits().max_buffer_size as usize / std::mem::size_of::<Instance>())
+            .min(u32::MAX as usize);
+
+        let quad_count = self.quads.len();
+        if quad_count > self.quad_capacity && self.quad_capacity < max_quads {
+            self.quad_capacity = quad_count.max(self.quad_capacity * 2).min(max_quads);
+            self.instance_buffer = Self::create_instance_buffer(device, self.quad_capacity);
+
+            //the new buffer starts out empty
+            self.dirty_quads = Some(0..quad_count);
+        }
 
+        if let Some(range) = self.dirty_quads.take() {
+            //removed quads can leave the range pointing past the end
+            let end = range.end.min(quad_count).min(self.quad_capacity);
+            let range = range.start.min(end)..end;
+            queue.write_buffer(
+                &self.instance_buffer,
+                (range.start * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&self.instances[range]),
+            );
+        }
+
+        if quad_count > self.quad_capacity {
+            return Err(RendererError::TooManyQuads {
+                count: quad_count,
+                max: self.quad_capacity,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn buffers(&self) -> Buffers<'_> {
         Buffers {
-            vertex_buffer,
-            index_buffer,
-            num_of_indices,
+            vertex_buffer: &self.vertex_buffer,
+            index_buffer: &self.index_buffer,
+            instance_buffer: &self.instance_buffer,
+            index_format: INDEX_FORMAT,
+            num_of_indices: QUAD_INDICES.len() as u32,
+            //quads past the capacity never made it to the gpu, capacity always fits in a u32
+            num_of_instances: self.quads.len().min(self.quad_capacity) as u32,
         }
     }
 }
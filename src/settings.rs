@@ -0,0 +1,60 @@
+use std::fmt;
+use std::str::FromStr;
+
+//how the world is fit into the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalingMode {
+    //one world unit per pixel, a bigger window shows more of the level
+    Window,
+    //the virtual resolution stretched as big as it fits, with bars on the sides that don't
+    Letterbox,
+    //like letterbox but only whole multiples of the virtual resolution so pixels stay sharp
+    IntegerScale,
+}
+
+impl FromStr for ScalingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "window" => Ok(ScalingMode::Window),
+            "letterbox" => Ok(ScalingMode::Letterbox),
+            "integer" => Ok(ScalingMode::IntegerScale),
+            _ => Err(format!(
+                "unknown scaling mode '{}', expected window, letterbox or integer",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ScalingMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ScalingMode::Window => "window",
+            ScalingMode::Letterbox => "letterbox",
+            ScalingMode::IntegerScale => "integer",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+//player facing options that don't change how the game plays
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub scaling: ScalingMode,
+    //how much of the world is visible (in world units) in the letterbox and integer modes
+    pub virtual_resolution: (u32, u32),
+    //shaking and punching the camera, off for players who get motion sick
+    pub screen_shake: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            scaling: ScalingMode::Letterbox,
+            virtual_resolution: (800, 600),
+            screen_shake: true,
+        }
+    }
+}
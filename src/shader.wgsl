@@ -6,29 +6,62 @@ struct CameraUniform{
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
 
+// corner of the shared unit quad, (0,0) to (1,1)
 struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) color: vec3<f32>,
+    @location(0) position: vec2<f32>,
+};
+
+struct InstanceInput {
+    @location(1) position: vec2<f32>,
+    @location(2) size: vec2<f32>,
+    @location(3) color: vec3<f32>,
+    // min uv in xy, max uv in zw
+    @location(4) uv_rect: vec4<f32>,
+    @location(5) tiling: vec2<f32>,
+    // radians counter clockwise around the middle of the quad
+    @location(6) rotation: f32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
+    // position inside the quad scaled by tiling, the fragment shader wraps it into the sprite
+    @location(1) local: vec2<f32>,
+    @location(2) uv_min: vec2<f32>,
+    @location(3) uv_max: vec2<f32>,
 };
 
 @vertex
 fn vs_main(
     model: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput {
     var out: VertexOutput;
-    out.color = model.color;
-    out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
+    out.color = instance.color;
+    // textures go top to bottom, the quad goes bottom to top
+    out.local = vec2<f32>(model.position.x, 1.0 - model.position.y) * instance.tiling;
+    out.uv_min = instance.uv_rect.xy;
+    out.uv_max = instance.uv_rect.zw;
+    let half = instance.size * 0.5;
+    let corner = model.position * instance.size - half;
+    let c = cos(instance.rotation);
+    let s = sin(instance.rotation);
+    let rotated = vec2<f32>(corner.x * c - corner.y * s, corner.x * s + corner.y * c);
+    let world_position = instance.position + half + rotated;
+    out.clip_position = camera.view_proj * vec4<f32>(world_position, 0.0, 1.0);
     return out;
 }
 
 // Fragment shader
 
+@group(1) @binding(0)
+var t_atlas: texture_2d<f32>;
+@group(1) @binding(1)
+var s_atlas: sampler;
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 1.0);
-}
\ No newline at end of file
+    let uv = mix(in.uv_min, in.uv_max, fract(in.local));
+    let texel = textureSample(t_atlas, s_atlas, uv);
+    return vec4<f32>(texel.rgb * in.color, texel.a);
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+use crate::ascii_map::merge_tiles;
+use crate::data::ParseError;
+use crate::level::{default_block_color, EntityDef, EntityKind, Level};
+use crate::world::QUAD_SIZE;
+
+//levels exported from the Tiled editor as json (.tmj)
+//
+//tile layers become blocks, every non empty tile counts as solid unless the layer has a
+//custom bool property "solid" set to false, and a color property tints its blocks.
+//objects are entities, their type (or class in Tiled 1.9) is "spawn", "enemy" or "pickup"
+//and their custom properties are passed along to the entity.
+//one Tiled tile is one QUAD_SIZE tile in the game no matter what the map's tile size is.
+pub fn parse(source: &str) -> Result<Level, ParseError> {
+    let map: Map = serde_json::from_str(source).map_err(|err| ParseError {
+        position: Some((err.line(), err.column())),
+        message: err.to_string(),
+    })?;
+
+    if map.orientation != "orthogonal" {
+        return Err(map_error(&format!(
+            "{} maps aren't supported, only orthogonal ones",
+            map.orientation
+        )));
+    }
+    if map.infinite {
+        return Err(map_error("infinite maps aren't supported"));
+    }
+    //every object position is divided by these
+    if map.tilewidth <= 0.0 || map.tileheight <= 0.0 {
+        return Err(map_error(&format!(
+            "map tiles are {}x{}, they need a size above 0",
+            map.tilewidth, map.tileheight
+        )));
+    }
+
+    let mut level = Level {
+        spawn: (0.0, 0.0),
+        blocks: Vec::new(),
+        entities: Vec::new(),
+    };
+    let mut spawn = None;
+
+    //layers are a stack so groups can push their children, reversed to keep Tiled's order
+    let mut layers: Vec<&Layer> = map.layers.iter().rev().collect();
+    while let Some(layer) = layers.pop() {
+        match layer {
+            Layer::Tilelayer(tiles) => {
+                if !tiles.properties.bool("solid").unwrap_or(true) {
+                    continue;
+                }
+                let data = match &tiles.data {
+                    Some(data) => data,
+                    None => {
+                        return Err(map_error(&format!(
+                            "tile layer '{}' has no csv data, export it without compression",
+                            tiles.name
+                        )))
+                    }
+                };
+                if tiles.width == 0 || tiles.height == 0 {
+                    return Err(map_error(&format!(
+                        "tile layer '{}' is {}x{}, it needs at least one tile",
+                        tiles.name, tiles.width, tiles.height
+                    )));
+                }
+                if data.len() != tiles.width * tiles.height {
+                    return Err(map_error(&format!(
+                        "tile layer '{}' has {} tiles, expected {}x{}",
+                        tiles.name,
+                        data.len(),
+                        tiles.width,
+                        tiles.height
+                    )));
+                }
+
+                //layers can be smaller than the map, rows count down from the top of the map
+                //so pad the grid with the empty rows below the layer
+                let mut solid: Vec<Vec<bool>> = data
+                    .chunks(tiles.width)
+                    .map(|row| row.iter().map(|gid| *gid != 0).collect())
+                    .collect();
+                solid.resize(map.height.max(tiles.height), Vec::new());
+
+                let color = match tiles.properties.string("color") {
+                    Some(color) => parse_color(color).ok_or_else(|| {
+                        map_error(&format!(
+                            "tile layer '{}' has an invalid color '{}'",
+                            tiles.name, color
+                        ))
+                    })?,
+                    None => default_block_color(),
+                };
+                for mut block in merge_tiles(&solid) {
+                    block.color = color;
+                    level.blocks.push(block);
+                }
+            }
+            Layer::Objectgroup(group) => {
+                for object in group.objects.iter() {
+                    //tile objects are anchored at their bottom left, everything else at the top left
+                    let bottom = if object.gid.is_some() {
+                        object.y
+                    } else {
+                        object.y + object.height
+                    };
+                    let pos = (
+                        object.x / map.tilewidth * QUAD_SIZE,
+                        (map.height as f32 - bottom / map.tileheight) * QUAD_SIZE,
+                    );
+
+                    let kind = if object.kind.is_empty() {
+                        &object.class
+                    } else {
+                        &object.kind
+                    };
+                    let kind = match kind.as_str() {
+                        "spawn" => {
+                            if spawn.is_some() {
+                                return Err(map_error(&format!(
+                                    "second spawn point (object {})",
+                                    object.id
+                                )));
+                            }
+                            spawn = Some(pos);
+                            continue;
+                        }
+                        "enemy" => EntityKind::Enemy,
+                        "pickup" => EntityKind::Pickup,
+                        other => {
+                            return Err(map_error(&format!(
+                                "object {} has an unknown type '{}'",
+                                object.id, other
+                            )))
+                        }
+                    };
+
+                    let mut entity = EntityDef::new(kind, pos);
+                    entity.properties = object.properties.to_strings();
+                    level.entities.push(entity);
+                }
+            }
+            Layer::Group(group) => layers.extend(group.layers.iter().rev()),
+            Layer::Imagelayer(_) => {}
+        }
+    }
+
+    level.spawn = spawn.ok_or_else(|| map_error("map has no object with type 'spawn'"))?;
+    Ok(level)
+}
+
+//what's in the map rather than its syntax is wrong, json has no position for that
+fn map_error(message: &str) -> ParseError {
+    ParseError::invalid(message)
+}
+
+//Tiled writes colors as #rrggbb or #aarrggbb
+fn parse_color(color: &str) -> Option<(f32, f32, f32)> {
+    let hex = color.strip_prefix('#')?;
+    let rgb = match hex.len() {
+        6 => hex,
+        8 => &hex[2..],
+        _ => return None,
+    };
+    let channel = |i: usize| {
+        u8::from_str_radix(rgb.get(i..i + 2)?, 16)
+            .ok()
+            .map(|value| value as f32 / 255.0)
+    };
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[derive(Deserialize)]
+struct Map {
+    height: usize,
+    tilewidth: f32,
+    tileheight: f32,
+    #[serde(default = "default_orientation")]
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    layers: Vec<Layer>,
+}
+
+fn default_orientation() -> String {
+    "orthogonal".to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Layer {
+    Tilelayer(TileLayer),
+    Objectgroup(ObjectGroup),
+    Group(GroupLayer),
+    Imagelayer(IgnoredAny),
+}
+
+#[derive(Deserialize)]
+struct TileLayer {
+    name: String,
+    width: usize,
+    height: usize,
+    //missing when the layer is base64/compressed
+    data: Option<Vec<u32>>,
+    #[serde(default)]
+    properties: Properties,
+}
+
+#[derive(Deserialize)]
+struct ObjectGroup {
+    objects: Vec<Object>,
+}
+
+#[derive(Deserialize)]
+struct GroupLayer {
+    layers: Vec<Layer>,
+}
+
+#[derive(Deserialize)]
+struct Object {
+    id: u32,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    height: f32,
+    gid: Option<u32>,
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    properties: Properties,
+}
+
+#[derive(Deserialize, Default)]
+struct Properties(Vec<Property>);
+
+#[derive(Deserialize)]
+struct Property {
+    name: String,
+    value: serde_json::Value,
+}
+
+impl Properties {
+    fn get(&self, name: &str) -> Option<&serde_json::Value> {
+        self.0
+            .iter()
+            .find(|property| property.name == name)
+            .map(|property| &property.value)
+    }
+
+    fn bool(&self, name: &str) -> Option<bool> {
+        self.get(name)?.as_bool()
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        self.get(name)?.as_str()
+    }
+
+    fn to_strings(&self) -> HashMap<String, String> {
+        self.0
+            .iter()
+            .map(|property| {
+                let value = match &property.value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                (property.name.clone(), value)
+            })
+            .collect()
+    }
+}
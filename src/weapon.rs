@@ -0,0 +1,364 @@
+use std::path::Path;
+
+use cgmath::{vec2, Point2, Vector2};
+use serde::Deserialize;
+
+use crate::data::{self, DataError};
+use crate::physics::Aabb;
+use crate::world::Entity;
+
+//who fired a projectile, it only hurts the other side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Team {
+    Player,
+    Enemy,
+}
+
+//what a weapon shoots
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Projectile {
+    Bullet,
+    //explodes when it hits something or runs out of range, hurting everything in the radius
+    Rocket { splash_radius: f32 },
+    //no projectile, whatever is first in line within range gets hit right away
+    Hitscan,
+}
+
+//a weapon as it's written in assets/weapons.ron
+//
+//(
+//    name: "shotgun",
+//    damage: 8.0,
+//    fire_rate: 1.2,
+//    magazine_size: 6,
+//    reload_time: 1.5,
+//    spread: 20.0,
+//    pellets: 8,
+//    projectile_speed: 1000.0,
+//    range: 500.0,
+//    projectile: Bullet,
+//)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeaponDef {
+    pub name: String,
+    pub damage: f32,        //per projectile
+    pub fire_rate: f32,     //shots per second
+    pub magazine_size: u32, //shots before reloading
+    pub reload_time: f32,   //seconds
+    pub spread: f32,        //degrees, the whole cone projectiles can leave in
+    //projectiles per shot
+    #[serde(default = "default_pellets")]
+    pub pellets: u32,
+    //pixels per second, unused by hitscan
+    #[serde(default)]
+    pub projectile_speed: f32,
+    pub range: f32, //pixels a projectile travels before it's gone
+    #[serde(default = "default_projectile_size")]
+    pub projectile_size: f32,
+    //pixels per second the shooter gets pushed back per shot
+    #[serde(default)]
+    pub recoil: f32,
+    pub projectile: Projectile,
+}
+
+fn default_pellets() -> u32 {
+    1
+}
+
+fn default_projectile_size() -> f32 {
+    12.0
+}
+
+impl WeaponDef {
+    //what the player carries when assets/weapons.ron can't be loaded
+    pub fn pistol() -> Self {
+        Self {
+            name: "pistol".to_string(),
+            damage: 10.0,
+            fire_rate: 4.0,
+            magazine_size: 12,
+            reload_time: 1.0,
+            spread: 2.0,
+            pellets: 1,
+            projectile_speed: 900.0,
+            range: 1350.0,
+            projectile_size: default_projectile_size(),
+            recoil: 0.0,
+            projectile: Projectile::Bullet,
+        }
+    }
+
+    //what the file format can't catch. a weapon without a fire rate or a magazine never
+    //fires, one without pellets fires nothing and a projectile without a speed never moves
+    //and is gone the tick it's fired
+    pub fn check(&self, path: &Path) -> Result<(), DataError> {
+        let problem = if self.fire_rate <= 0.0 {
+            "needs a fire_rate above 0"
+        } else if self.magazine_size == 0 {
+            "needs a magazine_size above 0"
+        } else if self.pellets == 0 {
+            "needs at least one pellet"
+        } else if self.projectile != Projectile::Hitscan && self.projectile_speed <= 0.0 {
+            "needs a projectile_speed above 0"
+        } else if self.damage < 0.0 {
+            "can't have a negative damage"
+        } else if self.range < 0.0 {
+            "can't have a negative range"
+        } else if self.reload_time < 0.0 {
+            "can't have a negative reload_time"
+        } else {
+            return Ok(());
+        };
+        Err(DataError::Invalid {
+            path: path.to_path_buf(),
+            message: format!("weapon '{}' {}", self.name, problem),
+        })
+    }
+}
+
+//the list of weapons in path, rejected as a whole when one of them doesn't check out
+pub fn load_weapons(path: impl AsRef<Path>) -> Result<Vec<WeaponDef>, DataError> {
+    let path = path.as_ref();
+    let weapons: Vec<WeaponDef> = data::load_ron(path)?;
+    for weapon in weapons.iter() {
+        weapon.check(path)?;
+    }
+    Ok(weapons)
+}
+
+//a weapon someone is holding, with its ammo and timers
+#[derive(Debug, Clone)]
+pub struct Weapon {
+    pub def: WeaponDef,
+    pub ammo: u32,
+    cooldown: f32,            //seconds until the next shot
+    reload_left: Option<f32>, //seconds until the magazine is full again
+}
+
+impl Weapon {
+    pub fn new(def: WeaponDef) -> Self {
+        Self {
+            ammo: def.magazine_size,
+            def,
+            cooldown: 0.0,
+            reload_left: None,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        if let Some(left) = self.reload_left {
+            if left <= dt {
+                self.ammo = self.def.magazine_size;
+                self.reload_left = None;
+            } else {
+                self.reload_left = Some(left - dt);
+            }
+        }
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reload_left.is_some()
+    }
+
+    //does nothing when the magazine is already full or a reload is going
+    pub fn reload(&mut self) {
+        if !self.is_reloading() && self.ammo < self.def.magazine_size {
+            self.reload_left = Some(self.def.reload_time);
+        }
+    }
+
+    pub fn can_fire(&self) -> bool {
+        self.cooldown <= 0.0 && self.ammo > 0 && !self.is_reloading()
+    }
+
+    //uses up a shot and returns true when the weapon was ready, an empty magazine
+    //starts reloading on its own
+    pub fn try_fire(&mut self) -> bool {
+        if !self.can_fire() {
+            return false;
+        }
+        self.cooldown = 1.0 / self.def.fire_rate;
+        self.ammo -= 1;
+        if self.ammo == 0 {
+            self.reload();
+        }
+        true
+    }
+}
+
+pub struct Bullet {
+    id: usize,
+    pub pos: Point2<f32>,
+    pub prev_pos: Point2<f32>, //position before the last tick, used for interpolation
+    pub vel: Vector2<f32>,     //pixels per second
+    pub size: Vector2<f32>,
+    pub age: f32,           //seconds since it was fired
+    pub lifetime: f32,      //seconds it lives for
+    pub damage: f32,
+    pub splash_radius: f32, //0 for bullets that don't explode
+    pub team: Team,
+}
+
+impl Bullet {
+    //pos is the middle of the bullet
+    pub fn new(
+        id: usize,
+        pos: Point2<f32>,
+        vel: Vector2<f32>,
+        def: &WeaponDef,
+        team: Team,
+    ) -> Self {
+        let size = vec2::<f32>(def.projectile_size, def.projectile_size);
+        let pos = pos - size / 2.0;
+        let lifetime = if def.projectile_speed > 0.0 {
+            def.range / def.projectile_speed
+        } else {
+            0.0
+        };
+        let splash_radius = match def.projectile {
+            Projectile::Rocket { splash_radius } => splash_radius,
+            _ => 0.0,
+        };
+        Self {
+            id,
+            pos,
+            prev_pos: pos,
+            vel,
+            size,
+            age: 0.0,
+            lifetime,
+            damage: def.damage,
+            splash_radius,
+            team,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.pos, self.size)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.age >= self.lifetime
+    }
+}
+
+impl Entity for Bullet {
+    fn get_id(&self) -> usize {
+        self.id
+    }
+
+    fn get_pos(&self) -> &Point2<f32> {
+        &self.pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "weapons.ron";
+
+    fn problem(def: &WeaponDef) -> Option<String> {
+        def.check(Path::new(PATH)).err().map(|err| err.to_string())
+    }
+
+    //fires 4 times a second, 12 shots a magazine and a second to reload
+    fn pistol() -> Weapon {
+        Weapon::new(WeaponDef::pistol())
+    }
+
+    #[test]
+    fn shipped_weapons_check_out() {
+        let weapons = load_weapons("assets/weapons.ron").unwrap();
+        assert!(!weapons.is_empty());
+    }
+
+    #[test]
+    fn check_rejects_weapons_that_cant_work() {
+        //the field the message should name and how to break it
+        type Breaks = fn(&mut WeaponDef);
+        let broken: [(&str, Breaks); 7] = [
+            ("fire_rate", |def| def.fire_rate = 0.0),
+            ("magazine_size", |def| def.magazine_size = 0),
+            ("pellet", |def| def.pellets = 0),
+            ("projectile_speed", |def| def.projectile_speed = 0.0),
+            ("damage", |def| def.damage = -1.0),
+            ("range", |def| def.range = -1.0),
+            ("reload_time", |def| def.reload_time = -1.0),
+        ];
+        for (field, breaks) in broken {
+            let mut def = WeaponDef::pistol();
+            breaks(&mut def);
+            let message = problem(&def).unwrap_or_else(|| panic!("{} wasn't caught", field));
+            assert!(message.starts_with(PATH), "{}", message);
+            assert!(message.contains(field), "{}", message);
+        }
+
+        assert_eq!(problem(&WeaponDef::pistol()), None);
+        //hitscan doesn't fly so it doesn't need a speed
+        let mut laser = WeaponDef::pistol();
+        laser.projectile = Projectile::Hitscan;
+        laser.projectile_speed = 0.0;
+        assert_eq!(problem(&laser), None);
+    }
+
+    #[test]
+    fn one_bad_weapon_rejects_the_file() {
+        match load_weapons("tests/fixtures/bad_weapons.ron") {
+            Err(DataError::Invalid { message, .. }) => assert!(message.contains("jammed")),
+            other => panic!("expected an invalid weapon, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn cooldown_spaces_out_shots() {
+        let mut weapon = pistol();
+        assert!(weapon.try_fire());
+        assert!(!weapon.try_fire());
+
+        weapon.update(0.2);
+        assert!(!weapon.can_fire());
+        weapon.update(0.05);
+        assert!(weapon.try_fire());
+        assert_eq!(weapon.ammo, 10);
+    }
+
+    #[test]
+    fn empty_magazine_reloads_on_its_own() {
+        let mut weapon = pistol();
+        for _ in 0..12 {
+            assert!(weapon.try_fire());
+            weapon.update(0.25);
+        }
+        assert_eq!(weapon.ammo, 0);
+        assert!(weapon.is_reloading());
+        assert!(!weapon.try_fire());
+
+        //the last shot's update already counted towards the reload
+        weapon.update(0.5);
+        assert!(weapon.is_reloading());
+        weapon.update(0.25);
+        assert!(!weapon.is_reloading());
+        assert_eq!(weapon.ammo, 12);
+        assert!(weapon.try_fire());
+    }
+
+    #[test]
+    fn reloading_blocks_firing_and_a_full_magazine_doesnt_reload() {
+        let mut weapon = pistol();
+        weapon.reload();
+        assert!(!weapon.is_reloading());
+
+        assert!(weapon.try_fire());
+        weapon.update(0.25);
+        weapon.reload();
+        assert!(weapon.is_reloading());
+        assert!(!weapon.try_fire());
+
+        weapon.update(1.0);
+        assert_eq!(weapon.ammo, 12);
+        assert!(weapon.try_fire());
+    }
+}
@@ -0,0 +1,845 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cgmath::{point2, point3, vec2, InnerSpace, Point2, Point3, Vector2};
+
+use crate::behavior::BehaviorTree;
+use crate::enemy::{self, Enemy, EnemyTypes};
+use crate::health::Health;
+use crate::level::{EntityKind, Level};
+use crate::navigation::{JumpArc, NavGraph};
+use crate::physics::{move_and_collide, ray_cast, Aabb, MoveResult};
+use crate::weapon::{Bullet, Projectile, Team, Weapon, WeaponDef};
+
+//size of one tile, everything in the world is measured in multiples of it
+pub const QUAD_SIZE: f32 = 50.0;
+
+//how everything that walks around falls and jumps, enemies have to share the player's
+//numbers because the navigation graph's jumps are worked out from the player's arc
+pub const GRAVITY: f32 = 2160.0; //pixels per second squared
+pub const JUMP_IMPULSE: f32 = 840.0; //pixels per second
+pub const MAX_FALL_SPEED: f32 = 720.0; //pixels per second
+
+pub const SIMULATION_HZ: u32 = 120;
+pub const TIMESTEP: f32 = 1.0 / SIMULATION_HZ as f32;
+
+//how quickly knockback wears off, higher stops sooner
+const KNOCKBACK_FRICTION: f32 = 8.0;
+//how hard things get pushed (pixels per second) by a bullet, touching an enemy and
+//the middle of an explosion
+const BULLET_KNOCKBACK: f32 = 150.0;
+const CONTACT_KNOCKBACK: Vector2<f32> = vec2::<f32>(400.0, 500.0);
+const EXPLOSION_KNOCKBACK: f32 = 900.0;
+//falling this far below the lowest block kills
+const KILL_DEPTH: f32 = 10.0 * QUAD_SIZE;
+
+pub trait Entity {
+    fn get_id(&self) -> usize;
+    fn get_pos(&self) -> &Point2<f32>;
+}
+
+//what the player is holding down, filled in by whoever drives the world
+//(the window event loop or a test)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Input {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    //latched when jump goes down and cleared by the next tick, so a tap that
+    //happens between two ticks isn't lost and holding jump doesn't bunny hop
+    pub jump_pressed: bool,
+    pub fire: bool,
+    //direction to shoot in, the way the player is facing when there's none
+    pub aim: Option<Vector2<f32>>,
+    //these are latched like jump_pressed
+    pub select_weapon: Option<usize>, //index into the player's weapons
+    pub cycle_weapon: i32,            //steps forward (or back when negative) through the weapons
+    pub reload: bool,
+}
+
+impl Input {
+    pub fn set_jump(&mut self, pressed: bool) {
+        if pressed && !self.jump {
+            self.jump_pressed = true;
+        }
+        self.jump = pressed;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facing {
+    Left,
+    Right,
+}
+
+impl Facing {
+    pub fn sign(&self) -> f32 {
+        match self {
+            Facing::Left => -1.0,
+            Facing::Right => 1.0,
+        }
+    }
+}
+
+//things that happened during a tick that whoever drives the world might want to react to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldEvent {
+    //the player hit the ground, speed is how fast they were falling in pixels per second
+    //and height how far they fell since they stopped going up
+    Landed { speed: f32, height: f32 },
+    //the player fired their weapon, direction is where they aimed before any spread
+    Shot { direction: Vector2<f32> },
+    //a hitscan shot went from one point to the other, stopping at whatever it hit
+    Hitscan { from: Point2<f32>, to: Point2<f32> },
+    //a rocket blew up, everything within radius of pos takes damage
+    Explosion { pos: Point2<f32>, radius: f32, damage: f32 },
+    //entity lost amount of health, the player's id is 0
+    Damaged { entity: usize, amount: f32 },
+    //entity ran out of health at pos, the player respawns right after and enemies are gone
+    Died { entity: usize, pos: Point2<f32> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Movement {
+    Idle,
+    Running,
+    Jumping,
+    Falling,
+}
+
+pub struct Player {
+    id: usize,
+    pub pos: Point2<f32>,
+    pub prev_pos: Point2<f32>, //position before the last tick, used for interpolation
+    pub vel: Vector2<f32>,     //pixels per second
+    pub walk: f32,             //pixels per second walked on its own, vel.x without knockback
+    pub is_grounded: bool,
+    pub facing: Facing,
+    pub knockback: Vector2<f32>, //pixels per second added on top of walking, wears off
+    pub health: Health,
+    pub weapons: Vec<Weapon>,
+    current_weapon: usize,
+    is_jumping: bool, //rising from a jump and jump hasn't been released yet
+    fall_from: f32,   //highest point since the player last stood on something or went up
+
+    pub speed: f32,          //pixels per second
+    pub gravity: f32,        //pixels per second squared
+    pub jump_impulse: f32,   //pixels per second
+    pub max_fall_speed: f32, //pixels per second
+    pub jump_cut: f32,       //upward velocity multiplier when jump is released mid jump
+}
+
+impl Player {
+    fn new(id: usize, pos: Point2<f32>) -> Self {
+        Self {
+            id,
+            pos,
+            prev_pos: pos,
+            vel: vec2::<f32>(0.0, 0.0),
+            walk: 0.0,
+            is_grounded: false,
+            facing: Facing::Right,
+            knockback: vec2::<f32>(0.0, 0.0),
+            health: Health::new(100.0, 1.0),
+            weapons: vec![Weapon::new(WeaponDef::pistol())],
+            current_weapon: 0,
+            is_jumping: false,
+            fall_from: pos.y,
+            speed: 300.0,
+            gravity: GRAVITY,
+            jump_impulse: JUMP_IMPULSE,
+            max_fall_speed: MAX_FALL_SPEED,
+            jump_cut: 0.5,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.pos, vec2::<f32>(QUAD_SIZE, QUAD_SIZE))
+    }
+
+    pub fn weapon(&self) -> &Weapon {
+        &self.weapons[self.current_weapon]
+    }
+
+    pub fn weapon_mut(&mut self) -> &mut Weapon {
+        &mut self.weapons[self.current_weapon]
+    }
+
+    pub fn current_weapon(&self) -> usize {
+        self.current_weapon
+    }
+
+    //replaces what the player carries, an empty list leaves them with what they had
+    pub fn set_weapons(&mut self, defs: Vec<WeaponDef>) {
+        if defs.is_empty() {
+            return;
+        }
+        self.weapons = defs.into_iter().map(Weapon::new).collect();
+        self.current_weapon = 0;
+    }
+
+    //slots the player doesn't have are ignored
+    pub fn select_weapon(&mut self, slot: usize) {
+        if slot < self.weapons.len() {
+            self.current_weapon = slot;
+        }
+    }
+
+    pub fn cycle_weapon(&mut self, steps: i32) {
+        let count = self.weapons.len() as i32;
+        self.current_weapon = (self.current_weapon as i32 + steps).rem_euclid(count) as usize;
+    }
+
+    pub fn movement(&self) -> Movement {
+        if !self.is_grounded {
+            if self.vel.y > 0.0 {
+                Movement::Jumping
+            } else {
+                Movement::Falling
+            }
+        } else if self.walk != 0.0 {
+            //not vel.x, being pushed around by knockback or recoil isn't running
+            Movement::Running
+        } else {
+            Movement::Idle
+        }
+    }
+
+    //a full jump at full speed, what the navigation graph's jump links are made from
+    pub fn jump_arc(&self) -> JumpArc {
+        JumpArc {
+            speed: self.speed,
+            gravity: self.gravity,
+            jump_impulse: self.jump_impulse,
+        }
+    }
+
+    //back at pos with full health and nothing carried over from before dying, weapons
+    //are kept but reloaded
+    fn respawn(&mut self, pos: Point2<f32>) {
+        self.pos = pos;
+        self.prev_pos = pos;
+        self.vel = vec2::<f32>(0.0, 0.0);
+        self.walk = 0.0;
+        self.knockback = vec2::<f32>(0.0, 0.0);
+        self.is_grounded = false;
+        self.is_jumping = false;
+        self.fall_from = pos.y;
+        self.health.reset();
+        for weapon in self.weapons.iter_mut() {
+            *weapon = Weapon::new(weapon.def.clone());
+        }
+    }
+}
+
+impl Entity for Player {
+    fn get_id(&self) -> usize {
+        self.id
+    }
+
+    fn get_pos(&self) -> &Point2<f32> {
+        &self.pos
+    }
+}
+
+pub struct Block {
+    id: usize,
+    pub pos: Point2<f32>,
+    pub length: Point2<usize>, //in tiles
+    pub color: Point3<f32>,
+}
+
+impl Block {
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(
+            self.pos,
+            vec2::<f32>(
+                QUAD_SIZE * self.length.x as f32,
+                QUAD_SIZE * self.length.y as f32,
+            ),
+        )
+    }
+}
+
+impl Entity for Block {
+    fn get_id(&self) -> usize {
+        self.id
+    }
+
+    fn get_pos(&self) -> &Point2<f32> {
+        &self.pos
+    }
+}
+
+//the whole simulation, no window or gpu needed to run it
+pub struct World {
+    pub spawn: Point2<f32>,
+    pub player: Player,
+    pub blocks: Vec<Block>,
+    pub bullets: Vec<Bullet>,
+    pub enemies: Vec<Enemy>,
+    pub navigation: NavGraph, //how enemies get around the blocks
+    pub input: Input,
+    kill_y: f32,
+    events: Vec<WorldEvent>,
+    rng: Rng,
+    next_id: usize,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::from_level(&Level::default())
+    }
+
+    //enemies all get the built in types
+    pub fn from_level(level: &Level) -> Self {
+        Self::from_level_with(level, &enemy::default_types())
+    }
+
+    //enemy_types are looked up by the "type" property of the level's enemies
+    pub fn from_level_with(level: &Level, enemy_types: &EnemyTypes) -> Self {
+        let spawn = point2::<f32>(level.spawn.0, level.spawn.1);
+        let player = Player::new(0, spawn);
+        let navigation = NavGraph::build(&[], player.aabb().size, player.jump_arc());
+        let mut world = Self {
+            spawn,
+            player,
+            blocks: Vec::new(),
+            bullets: Vec::new(),
+            enemies: Vec::new(),
+            navigation,
+            input: Input::default(),
+            kill_y: 0.0,
+            events: Vec::new(),
+            rng: Rng::new(0x5eed),
+            next_id: 1,
+        };
+
+        for block in level.blocks.iter() {
+            world.add_block(
+                point2::<f32>(block.pos.0, block.pos.1),
+                point2::<usize>(block.length.0, block.length.1),
+                point3::<f32>(block.color.0, block.color.1, block.color.2),
+            );
+        }
+        world.kill_y = world.bounds().pos.y - KILL_DEPTH;
+        world.rebuild_navigation();
+
+        //enemies of the same type share one behavior tree
+        let mut trees: HashMap<&str, Arc<BehaviorTree>> = HashMap::new();
+        for entity in level.entities.iter() {
+            if entity.kind != EntityKind::Enemy {
+                continue;
+            }
+            let kind = entity
+                .properties
+                .get("type")
+                .map(String::as_str)
+                .unwrap_or(enemy::DEFAULT_TYPE);
+            let (kind, def) = match enemy_types.get_key_value(kind) {
+                Some((kind, def)) => (kind.as_str(), def),
+                None => {
+                    log::warn!("unknown enemy type '{}', it's left out", kind);
+                    continue;
+                }
+            };
+            let tree = trees
+                .entry(kind)
+                .or_insert_with(|| Arc::new(BehaviorTree::new(&def.behavior)));
+            let id = world.new_id();
+            let pos = point2::<f32>(entity.pos.0, entity.pos.1);
+            let mut enemy = Enemy::new(id, pos, def, Arc::clone(tree));
+            //a level can make one enemy tougher than the rest of its type
+            if let Some(health) = entity.properties.get("health").and_then(|h| h.parse().ok()) {
+                enemy.health = Health::new(health, enemy.health.invulnerability);
+            }
+            world.enemies.push(enemy);
+        }
+
+        world
+    }
+
+    pub fn add_block(
+        &mut self,
+        pos: Point2<f32>,
+        length: Point2<usize>,
+        color: Point3<f32>,
+    ) -> usize {
+        let id = self.new_id();
+        self.blocks.push(Block {
+            id,
+            pos,
+            length,
+            color,
+        });
+        id
+    }
+
+    //the graph is only built when a level is loaded, blocks added afterwards need this
+    //to be called before enemies know about them
+    pub fn rebuild_navigation(&mut self) {
+        let blocks: Vec<Aabb> = self.blocks.iter().map(|block| block.aabb()).collect();
+        self.navigation = NavGraph::build(&blocks, self.player.aabb().size, self.player.jump_arc());
+    }
+
+    fn new_id(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    //area covered by the level's blocks and the spawn point
+    pub fn bounds(&self) -> Aabb {
+        let spawn = Aabb::new(self.spawn, vec2::<f32>(QUAD_SIZE, QUAD_SIZE));
+        self.blocks
+            .iter()
+            .map(|block| block.aabb())
+            .fold(spawn, |bounds, block| bounds.union(&block))
+    }
+
+    //what happened during the last tick, cleared when the next one starts
+    pub fn events(&self) -> &[WorldEvent] {
+        &self.events
+    }
+
+    //advances the simulation by dt seconds
+    pub fn tick(&mut self, dt: f32) {
+        let input = self.input;
+        self.input.jump_pressed = false;
+        self.input.select_weapon = None;
+        self.input.cycle_weapon = 0;
+        self.input.reload = false;
+        self.events.clear();
+
+        let player = &mut self.player;
+        player.prev_pos = player.pos;
+
+        //horizontal movement
+        player.walk = 0.0;
+        if input.right {
+            player.walk += player.speed;
+        }
+        if input.left {
+            player.walk -= player.speed;
+        }
+        if player.walk > 0.0 {
+            player.facing = Facing::Right;
+        } else if player.walk < 0.0 {
+            player.facing = Facing::Left;
+        }
+        //aiming turns the player towards the aim even while walking the other way
+        if let Some(aim) = input.aim {
+            if aim.x > 0.0 {
+                player.facing = Facing::Right;
+            } else if aim.x < 0.0 {
+                player.facing = Facing::Left;
+            }
+        }
+
+        //jumping
+        if input.jump_pressed && player.is_grounded {
+            player.vel.y = player.jump_impulse;
+            player.is_jumping = true;
+        }
+
+        //letting go of jump early cuts the jump short
+        if player.is_jumping && !input.jump {
+            if player.vel.y > 0.0 {
+                player.vel.y *= player.jump_cut;
+            }
+            player.is_jumping = false;
+        }
+
+        let blocks: Vec<Aabb> = self.blocks.iter().map(|block| block.aabb()).collect();
+        player.vel.x = player.walk;
+        let result = walk_and_fall(
+            player.aabb(),
+            &mut player.vel,
+            &mut player.knockback,
+            player.gravity,
+            player.max_fall_speed,
+            &blocks,
+            dt,
+        );
+        if player.vel.y <= 0.0 {
+            player.is_jumping = false;
+        }
+
+        let was_grounded = player.is_grounded;
+        player.is_grounded = result.touched(vec2::<f32>(0.0, 1.0));
+        if player.is_grounded && !was_grounded {
+            self.events.push(WorldEvent::Landed {
+                speed: -player.vel.y,
+                height: player.fall_from - result.pos.y,
+            });
+        }
+        if player.is_grounded || player.vel.y >= 0.0 {
+            player.fall_from = result.pos.y;
+        }
+        if player.is_grounded || result.touched(vec2::<f32>(0.0, -1.0)) {
+            player.vel.y = 0.0;
+        }
+
+        player.pos = result.pos;
+
+        //weapons, switching and reloading come first so a shot in the same tick uses them
+        if let Some(slot) = input.select_weapon {
+            player.select_weapon(slot);
+        }
+        if input.cycle_weapon != 0 {
+            player.cycle_weapon(input.cycle_weapon);
+        }
+        if input.reload {
+            player.weapon_mut().reload();
+        }
+        for weapon in player.weapons.iter_mut() {
+            weapon.update(dt);
+        }
+        if input.fire && player.weapon_mut().try_fire() {
+            let direction = input
+                .aim
+                .filter(|aim| aim.magnitude2() > 0.0)
+                .map(|aim| aim.normalize())
+                .unwrap_or(vec2::<f32>(player.facing.sign(), 0.0));
+            let def = player.weapon().def.clone();
+            let origin = player.aabb().center();
+            player.knockback -= direction * def.recoil;
+            self.fire(&def, origin, direction, Team::Player, &blocks);
+            self.events.push(WorldEvent::Shot { direction });
+        }
+
+        //projectiles fly straight and are gone once they hit a block or get too old,
+        //rockets blow up either way
+        for bullet in self.bullets.iter_mut() {
+            bullet.prev_pos = bullet.pos;
+            bullet.pos += bullet.vel * dt;
+            bullet.age += dt;
+        }
+        let mut explosions = Vec::new();
+        let mut player_hits = Vec::new();
+        let player_aabb = self.player.aabb();
+        let enemies = &mut self.enemies;
+        let events = &mut self.events;
+        self.bullets.retain(|bullet| {
+            let aabb = bullet.aabb();
+            let mut hit = blocks.iter().any(|block| block.overlaps(&aabb));
+            //rockets leave the damage to their explosion
+            let direct = bullet.splash_radius <= 0.0;
+            if bullet.team == Team::Enemy && player_aabb.overlaps(&aabb) {
+                hit = true;
+                if direct {
+                    player_hits.push((bullet.damage, bullet.vel.normalize() * BULLET_KNOCKBACK));
+                }
+            }
+            let target = enemies
+                .iter_mut()
+                .filter(|_| bullet.team == Team::Player)
+                .find(|enemy| enemy.aabb().overlaps(&aabb));
+            if let Some(enemy) = target {
+                hit = true;
+                if direct {
+                    let push = bullet.vel.normalize() * BULLET_KNOCKBACK;
+                    if enemy.health.damage(bullet.damage) {
+                        enemy.knockback += push;
+                        events.push(WorldEvent::Damaged {
+                            entity: enemy.get_id(),
+                            amount: bullet.damage,
+                        });
+                    }
+                }
+            }
+            if !hit && !bullet.is_expired() {
+                return true;
+            }
+            if bullet.splash_radius > 0.0 {
+                explosions.push((aabb.center(), bullet.splash_radius, bullet.damage));
+            }
+            false
+        });
+        for (damage, push) in player_hits {
+            self.damage_player(damage, push);
+        }
+        for (pos, radius, damage) in explosions {
+            self.explode(pos, radius, damage);
+        }
+
+        //enemies decide what to do and then move with the same physics as the player
+        let player_aabb = self.player.aabb();
+        let mut enemy_shots = Vec::new();
+        for enemy in self.enemies.iter_mut() {
+            enemy.prev_pos = enemy.pos;
+            if let Some(direction) = enemy.think(&player_aabb, &blocks, &self.navigation, dt) {
+                if let Some(weapon) = enemy.weapon.as_ref() {
+                    enemy_shots.push((weapon.def.clone(), enemy.aabb().center(), direction));
+                }
+            }
+
+            enemy.vel.x = enemy.walk;
+            let result = walk_and_fall(
+                enemy.aabb(),
+                &mut enemy.vel,
+                &mut enemy.knockback,
+                enemy.gravity,
+                enemy.max_fall_speed,
+                &blocks,
+                dt,
+            );
+            enemy.is_grounded = result.touched(vec2::<f32>(0.0, 1.0));
+            if enemy.is_grounded || result.touched(vec2::<f32>(0.0, -1.0)) {
+                enemy.vel.y = 0.0;
+            }
+            enemy.hit_wall =
+                result.touched(vec2::<f32>(1.0, 0.0)) || result.touched(vec2::<f32>(-1.0, 0.0));
+            enemy.pos = result.pos;
+        }
+        for (def, origin, direction) in enemy_shots {
+            self.fire(&def, origin, direction, Team::Enemy, &blocks);
+        }
+
+        //touching an enemy hurts and throws the player away from it
+        let player_aabb = self.player.aabb();
+        if let Some(enemy) = self
+            .enemies
+            .iter()
+            .find(|enemy| enemy.aabb().overlaps(&player_aabb))
+        {
+            let away = if player_aabb.center().x < enemy.aabb().center().x {
+                -1.0
+            } else {
+                1.0
+            };
+            let push = vec2::<f32>(CONTACT_KNOCKBACK.x * away, CONTACT_KNOCKBACK.y);
+            let damage = enemy.contact_damage;
+            self.damage_player(damage, push);
+        }
+
+        if self.player.pos.y < self.kill_y {
+            self.player.health.kill();
+        }
+        for enemy in self.enemies.iter_mut() {
+            if enemy.pos.y < self.kill_y {
+                enemy.health.kill();
+            }
+        }
+
+        self.player.health.update(dt);
+        for enemy in self.enemies.iter_mut() {
+            enemy.health.update(dt);
+        }
+
+        let events = &mut self.events;
+        self.enemies.retain(|enemy| {
+            if enemy.health.is_dead() {
+                events.push(WorldEvent::Died {
+                    entity: enemy.get_id(),
+                    pos: enemy.pos,
+                });
+            }
+            !enemy.health.is_dead()
+        });
+        if self.player.health.is_dead() {
+            self.events.push(WorldEvent::Died {
+                entity: self.player.get_id(),
+                pos: self.player.pos,
+            });
+            self.player.respawn(self.spawn);
+        }
+    }
+
+    //pushes the player and reports the damage when the hit lands
+    fn damage_player(&mut self, amount: f32, push: Vector2<f32>) {
+        if self.player.health.damage(amount) {
+            self.player.knockback += push;
+            self.events.push(WorldEvent::Damaged {
+                entity: self.player.get_id(),
+                amount,
+            });
+        }
+    }
+
+    //everything in radius gets hurt and thrown away from pos, less the further out it is
+    fn explode(&mut self, pos: Point2<f32>, radius: f32, damage: f32) {
+        self.events.push(WorldEvent::Explosion {
+            pos,
+            radius,
+            damage,
+        });
+
+        //how strongly something is hit and in which direction, None when out of reach
+        let falloff = |aabb: Aabb| {
+            let center = aabb.center();
+            let offset = center - pos;
+            let distance = offset.magnitude();
+            if distance >= radius {
+                return None;
+            }
+            let direction = if distance > 0.0 {
+                offset / distance
+            } else {
+                vec2::<f32>(0.0, 1.0)
+            };
+            Some((1.0 - distance / radius, direction))
+        };
+
+        for enemy in self.enemies.iter_mut() {
+            if let Some((strength, direction)) = falloff(enemy.aabb()) {
+                if enemy.health.damage(damage * strength) {
+                    enemy.knockback += direction * EXPLOSION_KNOCKBACK * strength;
+                    self.events.push(WorldEvent::Damaged {
+                        entity: enemy.get_id(),
+                        amount: damage * strength,
+                    });
+                }
+            }
+        }
+        if let Some((strength, direction)) = falloff(self.player.aabb()) {
+            self.damage_player(damage * strength, direction * EXPLOSION_KNOCKBACK * strength);
+        }
+    }
+
+    //spawns the projectiles of one shot, direction has to be normalized and every
+    //projectile gets its own bit of spread around it
+    fn fire(
+        &mut self,
+        def: &WeaponDef,
+        origin: Point2<f32>,
+        direction: Vector2<f32>,
+        team: Team,
+        blocks: &[Aabb],
+    ) {
+        let aim_angle = direction.y.atan2(direction.x);
+        let half_spread = def.spread.to_radians() / 2.0;
+
+        for _ in 0..def.pellets {
+            let angle = aim_angle + self.rng.range(-half_spread, half_spread);
+            let pellet = vec2::<f32>(angle.cos(), angle.sin());
+            match def.projectile {
+                Projectile::Hitscan => {
+                    let distance = self.hitscan(origin, pellet, def, team, blocks);
+                    self.events.push(WorldEvent::Hitscan {
+                        from: origin,
+                        to: origin + pellet * distance,
+                    });
+                }
+                Projectile::Bullet | Projectile::Rocket { .. } => {
+                    let id = self.new_id();
+                    self.bullets.push(Bullet::new(
+                        id,
+                        origin,
+                        pellet * def.projectile_speed,
+                        def,
+                        team,
+                    ));
+                }
+            }
+        }
+    }
+
+    //hurts the closest target of the other team in front of the first wall, returns
+    //how far the shot went
+    fn hitscan(
+        &mut self,
+        origin: Point2<f32>,
+        direction: Vector2<f32>,
+        def: &WeaponDef,
+        team: Team,
+        blocks: &[Aabb],
+    ) -> f32 {
+        let wall = blocks
+            .iter()
+            .filter_map(|block| ray_cast(origin, direction, block))
+            .fold(def.range, f32::min);
+        let push = direction * BULLET_KNOCKBACK;
+
+        if team == Team::Enemy {
+            return match ray_cast(origin, direction, &self.player.aabb()) {
+                Some(hit) if hit < wall => {
+                    self.damage_player(def.damage, push);
+                    hit
+                }
+                _ => wall,
+            };
+        }
+
+        let target = self
+            .enemies
+            .iter_mut()
+            .filter_map(|enemy| ray_cast(origin, direction, &enemy.aabb()).map(|hit| (hit, enemy)))
+            .filter(|(hit, _)| *hit < wall)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        match target {
+            Some((hit, enemy)) => {
+                if enemy.health.damage(def.damage) {
+                    enemy.knockback += push;
+                    self.events.push(WorldEvent::Damaged {
+                        entity: enemy.get_id(),
+                        amount: def.damage,
+                    });
+                }
+                hit
+            }
+            None => wall,
+        }
+    }
+}
+
+//knockback, gravity and blocks for anything that walks around like the player, vel.x has
+//to be set to how fast it walks on its own before calling. it's up to the caller what
+//landing or bumping into things means
+fn walk_and_fall(
+    aabb: Aabb,
+    vel: &mut Vector2<f32>,
+    knockback: &mut Vector2<f32>,
+    gravity: f32,
+    max_fall_speed: f32,
+    blocks: &[Aabb],
+    dt: f32,
+) -> MoveResult {
+    //knockback pushes things around on top of how they move without turning them
+    vel.x += knockback.x;
+    vel.y += knockback.y;
+    knockback.y = 0.0;
+    knockback.x *= (-KNOCKBACK_FRICTION * dt).exp();
+    if knockback.x.abs() < 1.0 {
+        knockback.x = 0.0;
+    }
+
+    vel.y = (vel.y - gravity * dt).max(-max_fall_speed);
+
+    //x and y are resolved separately so walking into the side of a block stops
+    //instead of ending up on top of it
+    let result = move_and_collide(aabb, *vel * dt, blocks);
+    if result.touched(vec2::<f32>(1.0, 0.0)) || result.touched(vec2::<f32>(-1.0, 0.0)) {
+        knockback.x = 0.0;
+    }
+    result
+}
+
+//small xorshift generator so spread doesn't need another dependency and a world
+//always plays out the same from the same input
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    //uniform between min and max
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,67 @@
+use shooter_game::data::DataError;
+use shooter_game::level::{EntityKind, Level};
+
+#[test]
+fn loads_blocks_spawn_and_entities() {
+    let level = Level::load("tests/fixtures/small.tmj").unwrap();
+
+    //the ground row and the floating platform, the decoration layer isn't solid
+    assert_eq!(level.blocks.len(), 2);
+    let ground = level.blocks.iter().find(|block| block.length == (6, 1)).unwrap();
+    assert_eq!(ground.pos, (0.0, 0.0));
+    let platform = level.blocks.iter().find(|block| block.length == (2, 1)).unwrap();
+    assert_eq!(platform.pos, (150.0, 100.0));
+
+    //#ff808080 with the alpha channel dropped
+    let grey = 128.0 / 255.0;
+    assert_eq!(ground.color, (grey, grey, grey));
+
+    assert_eq!(level.spawn, (50.0, 50.0));
+
+    assert_eq!(level.entities.len(), 1);
+    let enemy = &level.entities[0];
+    assert_eq!(enemy.kind, EntityKind::Enemy);
+    assert_eq!(enemy.pos, (200.0, 150.0));
+    assert_eq!(enemy.properties["patrol"], "true");
+    assert_eq!(enemy.properties["variant"], "grunt");
+}
+
+#[test]
+fn map_without_spawn_is_rejected() {
+    let err = Level::load("tests/fixtures/missing_spawn.tmj").unwrap_err();
+    //it's about the whole map so there's no line and column to point at
+    assert!(matches!(err, DataError::Invalid { .. }), "{:?}", err);
+    assert_eq!(
+        err.to_string(),
+        "tests/fixtures/missing_spawn.tmj: map has no object with type 'spawn'"
+    );
+}
+
+#[test]
+fn syntax_errors_report_line_and_column() {
+    match Level::load("tests/fixtures/broken.tmj") {
+        Err(DataError::Parse { line, column, .. }) => assert_eq!((line, column), (8, 26)),
+        other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn second_spawn_is_rejected() {
+    let err = Level::load("tests/fixtures/two_spawns.tmj").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "tests/fixtures/two_spawns.tmj: second spawn point (object 2)"
+    );
+}
+
+#[test]
+fn zero_sized_layer_is_rejected() {
+    let err = Level::load("tests/fixtures/empty_layer.tmj").unwrap_err();
+    assert!(err.to_string().contains("at least one tile"), "{}", err);
+}
+
+#[test]
+fn zero_tile_size_is_rejected() {
+    let err = Level::load("tests/fixtures/zero_tile_size.tmj").unwrap_err();
+    assert!(err.to_string().contains("0x32"), "{}", err);
+}
@@ -0,0 +1,136 @@
+use shooter_game::level::{BlockDef, Level};
+use shooter_game::world::{Entity, Movement, WorldEvent, QUAD_SIZE, SIMULATION_HZ, TIMESTEP};
+use shooter_game::World;
+
+const GROUND_TOP: f32 = 2.0 * QUAD_SIZE;
+const WALL_LEFT: f32 = 300.0;
+
+//a floor two tiles thick and a 6x1 block lying on it, the player drops in left of the block
+fn level() -> Level {
+    let block = |pos, length| BlockDef {
+        pos,
+        length,
+        color: (1.0, 1.0, 1.0),
+    };
+    Level {
+        spawn: (100.0, 300.0),
+        blocks: vec![
+            block((0.0, 0.0), (20, 2)),
+            block((WALL_LEFT, GROUND_TOP), (6, 1)),
+        ],
+        entities: Vec::new(),
+    }
+}
+
+fn tick_for(world: &mut World, seconds: f32) {
+    for _ in 0..(seconds * SIMULATION_HZ as f32) as u32 {
+        world.tick(TIMESTEP);
+    }
+}
+
+#[test]
+fn player_lands_on_the_ground() {
+    let mut world = World::from_level(&level());
+    tick_for(&mut world, 1.0);
+
+    assert!(world.player.is_grounded);
+    assert_eq!(world.player.pos.y, GROUND_TOP);
+    assert_eq!(world.player.pos.x, 100.0);
+}
+
+#[test]
+fn walking_into_a_block_stops_at_its_side() {
+    let mut world = World::from_level(&level());
+    tick_for(&mut world, 1.0);
+
+    world.input.right = true;
+    tick_for(&mut world, 2.0);
+
+    //flush against the block and still on the ground, not pushed on top of it
+    assert_eq!(world.player.pos.x, WALL_LEFT - QUAD_SIZE);
+    assert_eq!(world.player.pos.y, GROUND_TOP);
+    assert!(world.player.is_grounded);
+}
+
+#[test]
+fn a_held_jump_reaches_the_arc_height() {
+    let mut world = World::from_level(&level());
+    tick_for(&mut world, 1.0);
+
+    world.input.set_jump(true);
+    let mut highest = world.player.pos.y;
+    for _ in 0..SIMULATION_HZ {
+        world.tick(TIMESTEP);
+        highest = highest.max(world.player.pos.y);
+    }
+
+    let player = &world.player;
+    let expected = player.jump_impulse * player.jump_impulse / (2.0 * player.gravity);
+    let height = highest - GROUND_TOP;
+    //gravity is applied before moving each tick so it stays under the exact arc, by less
+    //than one tick of rising
+    assert!(height <= expected, "{} vs {}", height, expected);
+    assert!(expected - height < player.jump_impulse * TIMESTEP, "{} vs {}", height, expected);
+    //and it comes back down
+    assert!(player.is_grounded);
+}
+
+#[test]
+fn enemies_of_a_type_share_their_behavior_tree() {
+    let mut level = level();
+    level.entities = ron::from_str(
+        r#"[
+            (kind: Enemy, pos: (500.0, 100.0)),
+            (kind: Enemy, pos: (700.0, 100.0)),
+        ]"#,
+    )
+    .unwrap();
+    let world = World::from_level(&level);
+
+    assert_eq!(world.enemies.len(), 2);
+    assert!(std::ptr::eq(world.enemies[0].tree(), world.enemies[1].tree()));
+}
+
+#[test]
+fn an_enemy_knocked_off_the_level_dies() {
+    let mut level = level();
+    //a ledge out past the end of the floor with nothing under it
+    level.blocks.push(BlockDef {
+        pos: (1200.0, 300.0),
+        length: (2, 1),
+        color: (1.0, 1.0, 1.0),
+    });
+    level.entities = ron::from_str("[(kind: Enemy, pos: (1225.0, 350.0))]").unwrap();
+    let mut world = World::from_level(&level);
+    tick_for(&mut world, 0.5);
+    assert_eq!(world.enemies.len(), 1);
+
+    let id = world.enemies[0].get_id();
+    world.enemies[0].knockback.x = 1000.0;
+    let mut died = false;
+    for _ in 0..3 * SIMULATION_HZ {
+        world.tick(TIMESTEP);
+        died |= world
+            .events()
+            .iter()
+            .any(|event| matches!(event, WorldEvent::Died { entity, .. } if *entity == id));
+    }
+
+    assert!(died);
+    assert!(world.enemies.is_empty());
+}
+
+#[test]
+fn being_pushed_isnt_running() {
+    let mut world = World::from_level(&level());
+    tick_for(&mut world, 1.0);
+
+    world.player.knockback.x = 300.0;
+    world.tick(TIMESTEP);
+    assert!(world.player.vel.x > 0.0);
+    assert_eq!(world.player.movement(), Movement::Idle);
+
+    world.input.left = true;
+    world.tick(TIMESTEP);
+    assert_eq!(world.player.movement(), Movement::Running);
+}